@@ -1,7 +1,6 @@
-use eventsource_stream::Eventsource;
 use futures_lite::StreamExt;
 use replicate_rs::config::ReplicateConfig;
-use replicate_rs::predictions::PredictionClient;
+use replicate_rs::predictions::{PredictionClient, PredictionEvent};
 use serde_json::json;
 
 #[tokio::main]
@@ -21,9 +20,14 @@ async fn main() {
         .await
         .unwrap();
 
-    let mut stream = prediction.get_stream().await.unwrap();
+    let mut events = Box::pin(prediction.events().await.unwrap());
 
-    while let Some(event) = stream.next().await {
-        println!("RECEIVED EVENT: {:?}", event);
+    while let Some(event) = events.next().await {
+        match event.unwrap() {
+            PredictionEvent::Output(token) => print!("{token}"),
+            PredictionEvent::Logs(logs) => eprintln!("{logs}"),
+            PredictionEvent::Error(error) => eprintln!("prediction failed: {error}"),
+            PredictionEvent::Done => println!(),
+        }
     }
 }
@@ -0,0 +1,123 @@
+//! Utilities for interacting with the account endpoint.
+//!
+//! This includes the following:
+//! - [Get the authenticated account](https://replicate.com/docs/reference/http#account.get)
+//!
+use crate::config::ReplicateConfig;
+use crate::errors::{ReplicateError, ReplicateResult};
+
+use reqwest::Method;
+use serde::Deserialize;
+
+/// Whether an account belongs to an individual or an organization
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AccountType {
+    /// An individual user's account
+    User,
+    /// An organization's account
+    Organization,
+}
+
+/// Details for the account associated with an API token
+#[derive(Debug, Deserialize, Clone)]
+pub struct Account {
+    /// Whether the account is a user or organization, returned by Replicate as `type`
+    #[serde(rename = "type")]
+    pub account_type: AccountType,
+    /// Username of the account
+    pub username: String,
+    /// Display name of the account
+    pub name: String,
+    /// Github URL associated with the account
+    pub github_url: Option<String>,
+}
+
+/// A client for interacting with 'account' endpoint
+#[derive(Debug)]
+pub struct AccountClient {
+    config: ReplicateConfig,
+}
+
+impl AccountClient {
+    /// Create a new `AccountClient` based upon a `ReplicateConfig` object
+    pub fn from(config: ReplicateConfig) -> Self {
+        AccountClient { config }
+    }
+
+    /// Get the account associated with the configured API token. A rejected token fails with
+    /// `ReplicateError::InvalidCredentials`, so this doubles as a cheap credential check.
+    pub async fn get(&self) -> ReplicateResult<Account> {
+        let endpoint = self.config.endpoint("account");
+        let data = self
+            .config
+            .send(self.config.request(Method::GET, endpoint)?)
+            .await?;
+
+        serde_json::from_str(&data).map_err(ReplicateError::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use httpmock::prelude::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_get() {
+        let server = MockServer::start();
+
+        let account_mock = server.mock(|when, then| {
+            when.method(GET)
+                .path("/account")
+                .header("Authorization", "Token test-api-key");
+            then.status(200).json_body_obj(&json!({
+                "type": "user",
+                "username": "kyle",
+                "name": "Kyle",
+                "github_url": null
+            }));
+        });
+
+        let config = ReplicateConfig::test(server.base_url()).unwrap();
+        let account = AccountClient::from(config).get().await.unwrap();
+
+        assert_eq!(account.account_type, AccountType::User);
+        assert_eq!(account.username, "kyle");
+        assert_eq!(account.github_url, None);
+        account_mock.assert();
+    }
+
+    #[tokio::test]
+    async fn test_get_invalid_credentials() {
+        let server = MockServer::start();
+
+        server.mock(|when, then| {
+            when.method(GET).path("/account");
+            then.status(401).json_body_obj(&json!({
+                "title": "Unauthenticated",
+                "detail": "You did not pass a valid authentication token"
+            }));
+        });
+
+        let config = ReplicateConfig::test(server.base_url()).unwrap();
+        let err = AccountClient::from(config).get().await.unwrap_err();
+
+        assert!(matches!(err.inner(), ReplicateError::InvalidCredentials(_)));
+    }
+
+    #[test]
+    fn test_deserialize_account_type() {
+        let account: Account = serde_json::from_value(json!({
+            "type": "organization",
+            "username": "replicate",
+            "name": "Replicate",
+            "github_url": "https://github.com/replicate"
+        }))
+        .unwrap();
+
+        assert_eq!(account.account_type, AccountType::Organization);
+        assert_eq!(account.username, "replicate");
+    }
+}
@@ -0,0 +1,171 @@
+//! A single entry point which hands out clients for each group of endpoints.
+//!
+//! ```rust
+//! use replicate_rs::Replicate;
+//!
+//! let replicate = Replicate::from_token("r8_...");
+//! let predictions = replicate.predictions();
+//! let models = replicate.models();
+//! ```
+//!
+use crate::account::AccountClient;
+use crate::collections::CollectionClient;
+use crate::config::ReplicateConfig;
+use crate::deployments::DeploymentClient;
+use crate::errors::ReplicateResult;
+use crate::files::FileClient;
+use crate::hardware::HardwareClient;
+use crate::models::ModelClient;
+use crate::predictions::PredictionClient;
+use crate::trainings::TrainingClient;
+
+/// The top-level entry point to Replicate, an alias of `ReplicateClient`
+pub type Replicate = ReplicateClient;
+
+/// A client for all Replicate endpoints, sharing a single `ReplicateConfig`
+///
+/// Each sub-client is created from a clone of the config, which shares its connection pool, so
+/// they are cheap to create on demand.
+#[derive(Debug, Clone)]
+pub struct ReplicateClient {
+    config: ReplicateConfig,
+}
+
+impl ReplicateClient {
+    /// Create a client with the default config, which inherits its api_key from the
+    /// REPLICATE_API_KEY environment variable
+    pub fn new() -> ReplicateResult<Self> {
+        Ok(ReplicateClient::from(ReplicateConfig::from_env()?))
+    }
+
+    /// Create a client with the default config, which authenticates with `token` rather than
+    /// reading it from the environment
+    pub fn from_token(token: impl Into<String>) -> Self {
+        ReplicateClient::from(ReplicateConfig::from_token(token))
+    }
+
+    /// Create a new `ReplicateClient` based upon a `ReplicateConfig` object
+    pub fn from(config: ReplicateConfig) -> Self {
+        ReplicateClient { config }
+    }
+
+    /// The config shared by every sub-client
+    pub fn config(&self) -> &ReplicateConfig {
+        &self.config
+    }
+
+    /// A client for the 'predictions' endpoints
+    pub fn predictions(&self) -> PredictionClient {
+        PredictionClient::from(self.config.clone())
+    }
+
+    /// A client for the 'models' endpoints, sharing its cache of latest versions with every
+    /// other client created from this one
+    pub fn models(&self) -> ModelClient {
+        ModelClient::from(self.config.clone())
+    }
+
+    /// A client for the 'trainings' endpoints
+    pub fn trainings(&self) -> TrainingClient {
+        TrainingClient::from(self.config.clone())
+    }
+
+    /// A client for the 'deployments' endpoints
+    pub fn deployments(&self) -> DeploymentClient {
+        DeploymentClient::from(self.config.clone())
+    }
+
+    /// A client for the 'collections' endpoints
+    pub fn collections(&self) -> CollectionClient {
+        CollectionClient::from(self.config.clone())
+    }
+
+    /// A client for the 'files' endpoint
+    pub fn files(&self) -> FileClient {
+        FileClient::from(self.config.clone())
+    }
+
+    /// A client for the 'hardware' endpoint
+    pub fn hardware(&self) -> HardwareClient {
+        HardwareClient::from(self.config.clone())
+    }
+
+    /// A client for the 'account' endpoint
+    pub fn account(&self) -> AccountClient {
+        AccountClient::from(self.config.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use httpmock::prelude::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_sub_clients_share_config() {
+        let server = MockServer::start();
+
+        let hardware_mock = server.mock(|when, then| {
+            when.method(GET)
+                .path("/hardware")
+                .header("Authorization", "Token test-api-key");
+            then.status(200).json_body_obj(&json!([
+                {"name": "CPU", "sku": "cpu"}
+            ]));
+        });
+        let collections_mock = server.mock(|when, then| {
+            when.method(GET)
+                .path("/collections")
+                .header("Authorization", "Token test-api-key");
+            then.status(200).json_body_obj(&json!({
+                "next": null,
+                "previous": null,
+                "results": []
+            }));
+        });
+
+        let config = ReplicateConfig::test(server.base_url()).unwrap();
+        let client = Replicate::from(config);
+
+        assert_eq!(client.hardware().list().await.unwrap().len(), 1);
+        assert!(client
+            .collections()
+            .list()
+            .await
+            .unwrap()
+            .results
+            .is_empty());
+        hardware_mock.assert();
+        collections_mock.assert();
+    }
+
+    #[tokio::test]
+    async fn test_models_share_version_cache() {
+        let server = MockServer::start();
+
+        let model_mock = server.mock(|when, then| {
+            when.method(GET).path("/models/replicate/hello-world");
+            then.status(200).json_body_obj(&json!({
+                "latest_version": {
+                    "id": "5c7d5dc6dd8bf75c1acaa8565735e7986bc5b66206b55cca93cb72c9bf15ccaa",
+                    "created_at": "2022-04-26T19:29:04.418669Z",
+                    "cog_version": "0.3.0",
+                    "openapi_schema": null
+                }
+            }));
+        });
+
+        let config = ReplicateConfig::test(server.base_url()).unwrap();
+        let client = Replicate::from(config);
+
+        for _ in 0..2 {
+            client
+                .models()
+                .get_latest_version_cached("replicate", "hello-world")
+                .await
+                .unwrap();
+        }
+        model_mock.assert_hits(1);
+    }
+}
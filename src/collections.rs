@@ -0,0 +1,253 @@
+//! Utilities for interacting with all collection endpoints.
+//!
+//! This includes the following:
+//! - [Get a Collection of Models](https://replicate.com/docs/reference/http#collections.get)
+//! - [List Collections of Models](https://replicate.com/docs/reference/http#collections.list)
+//!
+use crate::config::ReplicateConfig;
+use crate::errors::{ReplicateError, ReplicateResult};
+use crate::models::Model;
+use crate::predictions::{Prediction, PredictionClient};
+
+use reqwest::Method;
+use serde::Deserialize;
+use serde_json::Value;
+
+/// Summary of a collection, as returned when listing collections
+#[derive(Debug, Clone, Deserialize)]
+pub struct CollectionSummary {
+    /// Name of the collection
+    pub name: String,
+    /// Slug used to retrieve the collection, such as `text-to-image`
+    pub slug: String,
+    /// A brief description of the collection
+    pub description: String,
+}
+
+/// Paginated list of available collections
+#[derive(Debug, Deserialize)]
+pub struct Collections {
+    /// Place in pagination
+    pub next: Option<String>,
+    /// Place in pagination
+    pub previous: Option<String>,
+    /// List of collections
+    #[serde(default)]
+    pub results: Vec<CollectionSummary>,
+}
+
+/// A collection of models curated by Replicate
+#[derive(Debug, Deserialize)]
+pub struct Collection {
+    /// Name of the collection
+    pub name: String,
+    /// Slug used to retrieve the collection, such as `text-to-image`
+    pub slug: String,
+    /// A brief description of the collection
+    pub description: String,
+    /// All models in the collection
+    #[serde(default)]
+    pub models: Vec<Model>,
+}
+
+impl Collection {
+    /// Create a prediction on the model at `index` in the collection, with `client`
+    ///
+    /// The model's latest version as listed in the collection is used, so no extra request is
+    /// made to look it up, and models listed without a version fall back to
+    /// `PredictionClient::create`. Returns `ReplicateError::InvalidRequest` if the collection
+    /// has no model at `index`.
+    pub async fn run_model(
+        &self,
+        index: usize,
+        client: &PredictionClient,
+        input: Value,
+    ) -> ReplicateResult<Prediction> {
+        let model = self.models.get(index).ok_or_else(|| {
+            ReplicateError::InvalidRequest(format!(
+                "collection {} has no model at index {index}, it has {} models",
+                self.slug,
+                self.models.len()
+            ))
+        })?;
+
+        match &model.latest_version {
+            Some(version) => client.create_with_version(&version.id, input, false).await,
+            None => client.create(&model.owner, &model.name, input, false).await,
+        }
+    }
+}
+
+/// A client for interacting with 'collections' endpoint
+#[derive(Debug)]
+pub struct CollectionClient {
+    config: ReplicateConfig,
+}
+
+impl CollectionClient {
+    /// Create a new `CollectionClient` based upon a `ReplicateConfig` object
+    pub fn from(config: ReplicateConfig) -> Self {
+        CollectionClient { config }
+    }
+
+    /// Get a collection, with all of its models
+    pub async fn get(&self, slug: &str) -> ReplicateResult<Collection> {
+        let endpoint = self.config.endpoint(&format!("collections/{slug}"));
+        let data = self
+            .config
+            .send(self.config.request(Method::GET, endpoint)?)
+            .await?;
+
+        serde_json::from_str(&data).map_err(ReplicateError::from)
+    }
+
+    /// List all collections
+    pub async fn list(&self) -> ReplicateResult<Collections> {
+        let endpoint = self.config.endpoint("collections");
+        let data = self
+            .config
+            .send(self.config.request(Method::GET, endpoint)?)
+            .await?;
+
+        serde_json::from_str(&data).map_err(ReplicateError::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use httpmock::prelude::*;
+    use serde_json::json;
+
+    fn model_json() -> Value {
+        json!({
+            "url": "https://replicate.com/nightmareai/real-esrgan",
+            "owner": "nightmareai",
+            "name": "real-esrgan",
+            "description": "Real-ESRGAN with optional face correction and adjustable upscale",
+            "visibility": "public",
+            "github_url": "https://github.com/xinntao/Real-ESRGAN",
+            "paper_url": "https://arxiv.org/abs/2107.10833",
+            "license_url": null,
+            "run_count": 1000,
+            "cover_image_url": "https://replicate.delivery/pbxt/cover.png",
+            "default_example": null,
+            "latest_version": {
+                "id": "42fed1c4974146d4d2414e2be2c5277c7fcf05fcc3a73abf41610695738c1d7b",
+                "created_at": "2023-09-08T16:19:34.765994Z",
+                "cog_version": "0.8.6",
+                "openapi_schema": null
+            }
+        })
+    }
+
+    #[tokio::test]
+    async fn test_get() {
+        let server = MockServer::start();
+
+        let collection_mock = server.mock(|when, then| {
+            when.method(GET).path("/collections/super-resolution");
+            then.status(200).json_body_obj(&json!({
+                "name": "Super resolution",
+                "slug": "super-resolution",
+                "description": "Upscaling models that create high-quality images from low-quality images.",
+                "models": [model_json()]
+            }));
+        });
+
+        let config = ReplicateConfig::test(server.base_url()).unwrap();
+        let collection = CollectionClient::from(config)
+            .get("super-resolution")
+            .await
+            .unwrap();
+
+        assert_eq!(collection.slug, "super-resolution");
+        assert_eq!(collection.models.len(), 1);
+        assert_eq!(collection.models[0].name, "real-esrgan");
+        collection_mock.assert();
+    }
+
+    #[tokio::test]
+    async fn test_list() {
+        let server = MockServer::start();
+
+        let collection_mock = server.mock(|when, then| {
+            when.method(GET).path("/collections");
+            then.status(200).json_body_obj(&json!({
+                "next": null,
+                "previous": null,
+                "results": [
+                    {
+                        "name": "Super resolution",
+                        "slug": "super-resolution",
+                        "description": "Upscaling models that create high-quality images from low-quality images."
+                    },
+                    {
+                        "name": "Image classification",
+                        "slug": "image-classification",
+                        "description": "Models that classify images."
+                    }
+                ]
+            }));
+        });
+
+        let config = ReplicateConfig::test(server.base_url()).unwrap();
+        let collections = CollectionClient::from(config).list().await.unwrap();
+
+        let slugs: Vec<&str> = collections
+            .results
+            .iter()
+            .map(|collection| collection.slug.as_str())
+            .collect();
+        assert_eq!(slugs, vec!["super-resolution", "image-classification"]);
+        collection_mock.assert();
+    }
+
+    #[tokio::test]
+    async fn test_run_model() {
+        let server = MockServer::start();
+
+        let prediction_mock =
+            server.mock(|when, then| {
+                when.method(POST).path("/predictions").json_body_obj(&json!({
+                "version": "42fed1c4974146d4d2414e2be2c5277c7fcf05fcc3a73abf41610695738c1d7b",
+                "input": {"image": "https://example.com/cat.png"},
+                "stream": false
+            }));
+                then.status(201).json_body_obj(&json!({
+                    "id": "1234",
+                    "model": "nightmareai/real-esrgan",
+                    "version": "42fed1c4974146d4d2414e2be2c5277c7fcf05fcc3a73abf41610695738c1d7b",
+                    "input": {"image": "https://example.com/cat.png"},
+                    "status": "starting",
+                    "created_at": "2023-09-08T16:19:34.765994657Z",
+                    "urls": {
+                        "cancel": format!("{}/predictions/1234/cancel", server.base_url()),
+                        "get": format!("{}/predictions/1234", server.base_url())
+                    }
+                }));
+            });
+
+        let collection: Collection = serde_json::from_value(json!({
+            "name": "Super resolution",
+            "slug": "super-resolution",
+            "description": "Upscaling models that create high-quality images from low-quality images.",
+            "models": [model_json()]
+        }))
+        .unwrap();
+
+        let config = ReplicateConfig::test(server.base_url()).unwrap();
+        let client = PredictionClient::from(config);
+        let input = json!({"image": "https://example.com/cat.png"});
+        let prediction = collection
+            .run_model(0, &client, input.clone())
+            .await
+            .unwrap();
+
+        assert_eq!(prediction.model, "nightmareai/real-esrgan");
+        prediction_mock.assert();
+
+        let err = collection.run_model(1, &client, input).await.unwrap_err();
+        assert!(matches!(err, ReplicateError::InvalidRequest(_)));
+    }
+}
@@ -1,16 +1,104 @@
 //! Utilities for high level configuration for Replicate clients.
 //!
-use crate::errors::{ReplicateError, ReplicateResult};
+use crate::errors::{get_error, retry_after, ReplicateError, ReplicateResult};
+use crate::models::VersionCache;
 use crate::{api_key, base_url};
-use anyhow::anyhow;
+use futures_timer::Delay;
+use reqwest::header::USER_AGENT;
+use reqwest::{IntoUrl, Method, RequestBuilder, Response, StatusCode};
+use std::fmt;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+/// A function applied to every outgoing request, after the standard headers have been set
+#[derive(Clone)]
+pub(crate) struct RequestHook(Arc<dyn Fn(RequestBuilder) -> RequestBuilder + Send + Sync>);
+
+impl fmt::Debug for RequestHook {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "RequestHook")
+    }
+}
+
+/// How the api token is attached to each request
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum AuthScheme {
+    /// `Authorization: Token <api_key>`, as expected by Replicate
+    #[default]
+    Token,
+    /// `Authorization: Bearer <api_key>`, as expected by some gateways
+    Bearer,
+    /// The api token as the entire value of a custom header, such as `X-Api-Key`
+    Header(String),
+}
+
+/// How requests are retried after rate limits, server errors and failed connections.
+///
+/// Idempotent `GET` requests are retried when Replicate responds with `429 Too Many Requests`
+/// or a `5xx` status. Other requests, such as creating a prediction, are only retried when no
+/// connection could be made, so they are never sent twice. Each retry waits twice as long as
+/// the one before, starting from `base_delay`, unless a `429` response gives a `Retry-After`.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Maximum number of attempts, including the first
+    pub max_attempts: u32,
+    /// Delay before the first retry
+    pub base_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(500),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before the next attempt, given how many attempts have already been made
+    pub fn delay(&self, attempts: u32) -> Duration {
+        self.base_delay
+            .saturating_mul(2u32.saturating_pow(attempts.saturating_sub(1)))
+    }
+}
 
 /// Config for Replicate Client
 #[derive(Clone, Debug)]
 pub struct ReplicateConfig {
     /// [API token](https://replicate.com/account/api-tokens) for replicate
-    api_key: Option<&'static str>,
+    api_key: Option<Arc<str>>,
     /// Endpoint url
     base_url: String,
+    /// How the api token is attached to requests
+    auth_scheme: AuthScheme,
+    /// Whether models may be resolved to their latest version implicitly
+    version_lookup: bool,
+    /// How long latest versions resolved when creating predictions are reused, if at all
+    version_lookup_ttl: Option<Duration>,
+    /// Customization applied to every outgoing request
+    request_hook: Option<RequestHook>,
+    /// Whether prediction streams may be served over plain http
+    insecure_streams: bool,
+    /// Maximum size in bytes of a response body
+    max_response_size: Option<usize>,
+    /// Number of stream events which may be read ahead of the consumer
+    #[cfg(feature = "tokio")]
+    stream_buffer: usize,
+    /// Maximum time a request may take, from connecting until the body is read
+    timeout: Option<Duration>,
+    /// Maximum time to wait for a connection to be established
+    connect_timeout: Option<Duration>,
+    /// Proxy every request is sent through
+    proxy: Option<reqwest::Proxy>,
+    /// How failed requests are retried, if at all
+    retry: Option<RetryPolicy>,
+    /// `User-Agent` header sent with every request
+    user_agent: String,
+    /// Http client shared by every clone of this config, built on first use
+    client: Arc<OnceLock<reqwest::Client>>,
+    /// Latest model versions shared by every clone of this config
+    version_cache: VersionCache,
 }
 
 impl Default for ReplicateConfig {
@@ -18,6 +106,21 @@ impl Default for ReplicateConfig {
         ReplicateConfig {
             api_key: None,
             base_url: base_url().to_string(),
+            auth_scheme: AuthScheme::Token,
+            version_lookup: true,
+            version_lookup_ttl: None,
+            request_hook: None,
+            insecure_streams: false,
+            max_response_size: None,
+            #[cfg(feature = "tokio")]
+            stream_buffer: 0,
+            timeout: None,
+            connect_timeout: None,
+            proxy: None,
+            retry: None,
+            user_agent: format!("replicate-rs/{}", env!("CARGO_PKG_VERSION")),
+            client: Arc::new(OnceLock::new()),
+            version_cache: VersionCache::default(),
         }
     }
 }
@@ -25,29 +128,797 @@ impl Default for ReplicateConfig {
 impl ReplicateConfig {
     /// Create a default config, inherits api_key from REPLICATE_API_KEY environment variable
     pub fn new() -> anyhow::Result<Self> {
+        anyhow::Ok(ReplicateConfig::from_env()?)
+    }
+
+    /// Create a default config and confirm its api_key is accepted by Replicate, with a request
+    /// to the account endpoint. Returns `ReplicateError::InvalidCredentials` if it is not.
+    pub async fn new_validated() -> ReplicateResult<Self> {
+        ReplicateConfig::from_env()?.validated().await
+    }
+
+    /// Create a default config which authenticates with `token`, rather than reading it from
+    /// the environment. Useful when tokens are fetched at runtime, such as from a secrets
+    /// manager, or differ between the users of a multi-tenant service.
+    pub fn from_token(token: impl Into<String>) -> Self {
+        ReplicateConfig {
+            api_key: Some(Arc::from(token.into())),
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn from_env() -> ReplicateResult<Self> {
         let api_key = api_key()?;
-        let base_url = base_url().to_string();
-        anyhow::Ok(ReplicateConfig {
-            api_key: Some(api_key),
-            base_url,
+        Ok(ReplicateConfig {
+            api_key: Some(Arc::from(api_key)),
+            ..Default::default()
         })
     }
 
+    /// Create a config pointed at a mock server, for use in tests
     #[cfg(test)]
     pub fn test(base_url: String) -> anyhow::Result<Self> {
         anyhow::Ok(ReplicateConfig {
-            api_key: Some("test-api-key"),
+            api_key: Some(Arc::from("test-api-key")),
             base_url,
+            ..Default::default()
         })
     }
 
-    pub(crate) fn get_api_key(&self) -> ReplicateResult<&'static str> {
-        self.api_key.ok_or(ReplicateError::MissingCredentials(
-            "REPLICATE_API_KEY not provided in environment variable".to_string(),
-        ))
+    async fn validated(self) -> ReplicateResult<Self> {
+        self.send(self.request(Method::GET, self.endpoint("account"))?)
+            .await?;
+        Ok(self)
+    }
+
+    /// Send requests to `base_url` instead of `https://api.replicate.com/v1`, such as a proxy
+    /// or gateway in front of Replicate, or a mock server in integration tests.
+    ///
+    /// Returns `ReplicateError::InvalidRequest` if `base_url` is not an absolute http or https
+    /// url. Trailing slashes are removed.
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> ReplicateResult<Self> {
+        let base_url = base_url.into();
+        let url = reqwest::Url::parse(&base_url).map_err(|err| {
+            ReplicateError::InvalidRequest(format!("invalid base url {base_url}: {err}"))
+        })?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(ReplicateError::InvalidRequest(format!(
+                "invalid base url {base_url}: expected an http or https url"
+            )));
+        }
+
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        // Versions cached from another base url may not exist behind this one
+        self.version_cache = VersionCache::default();
+        Ok(self)
+    }
+
+    /// Set how the api token is attached to requests. Defaults to `AuthScheme::Token`, which is
+    /// what Replicate expects, but proxies in front of Replicate may require something else.
+    pub fn with_auth_scheme(mut self, auth_scheme: AuthScheme) -> Self {
+        self.auth_scheme = auth_scheme;
+        self
+    }
+
+    /// Enable or disable resolving a model's latest version when creating predictions by
+    /// owner and name. When disabled, predictions must be created from a pinned version id
+    /// with `PredictionClient::create_with_version`.
+    pub fn with_version_lookup(mut self, enabled: bool) -> Self {
+        self.version_lookup = enabled;
+        self
+    }
+
+    /// Reuse the latest version resolved when creating a prediction by owner and name, such as
+    /// with `PredictionClient::create`, for up to `ttl` before looking it up again. Predictions
+    /// created meanwhile may run a version up to `ttl` old, after the model publishes a newer
+    /// one. By default (`None`) the latest version is looked up for every prediction.
+    ///
+    /// The cache is shared with `ModelClient::get_latest_version_cached` on clients created from
+    /// this config.
+    pub fn with_version_lookup_cache(mut self, ttl: Option<Duration>) -> Self {
+        self.version_lookup_ttl = ttl;
+        self
+    }
+
+    pub(crate) fn version_cache(&self) -> &VersionCache {
+        &self.version_cache
+    }
+
+    pub(crate) fn get_api_key(&self) -> ReplicateResult<&str> {
+        self.api_key
+            .as_deref()
+            .ok_or(ReplicateError::MissingCredentials(
+                "REPLICATE_API_KEY not provided in environment variable".to_string(),
+            ))
+    }
+
+    /// Allow prediction streams to be read from plain `http://` urls, such as a local mock
+    /// server. By default only `https://` stream urls are accepted.
+    pub fn with_insecure_streams(mut self, allowed: bool) -> Self {
+        self.insecure_streams = allowed;
+        self
+    }
+
+    /// Limit the size of response bodies read from Replicate. Responses larger than
+    /// `max_response_size` bytes fail with `ReplicateError::PayloadTooLarge` rather than being
+    /// read into memory. By default response size is unlimited.
+    pub fn with_max_response_size(mut self, max_response_size: usize) -> Self {
+        self.max_response_size = Some(max_response_size);
+        self
+    }
+
+    /// Buffer up to `capacity` server sent events when streaming predictions, to smooth out
+    /// bursts of output which arrive faster than they are consumed.
+    ///
+    /// The stream is read on a task spawned onto the current tokio runtime, which keeps receiving
+    /// events while the consumer is busy. Once the buffer is full nothing more is read until the
+    /// consumer takes an event, so the connection applies backpressure to Replicate rather than
+    /// memory growing without bound. Streams opened outside a tokio runtime are unbuffered, as
+    /// are streams by default.
+    ///
+    /// Requires the `tokio` feature, which is enabled by default.
+    #[cfg(feature = "tokio")]
+    pub fn with_stream_buffer(mut self, capacity: usize) -> Self {
+        self.stream_buffer = capacity;
+        self
+    }
+
+    /// Fail any request which takes longer than `timeout`, from connecting until its response
+    /// has been read, with a `ReplicateError::ClientError`. By default requests have no
+    /// timeout.
+    ///
+    /// Prediction streams are exempt, as they stay open for as long as the prediction runs.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Fail any request which cannot connect to Replicate within `timeout`. This applies to
+    /// prediction streams as well. By default there is no connect timeout.
+    ///
+    /// The http client is rebuilt with the new timeout, so this returns
+    /// `ReplicateError::InvalidRequest` if it cannot be built.
+    pub fn with_connect_timeout(mut self, timeout: Duration) -> ReplicateResult<Self> {
+        self.connect_timeout = Some(timeout);
+        self.rebuild_client()
+    }
+
+    /// Send every request through the proxy at `proxy_url`, such as `http://proxy.local:8080`.
+    /// By default the proxy is read from the `HTTP_PROXY` and `HTTPS_PROXY` environment
+    /// variables, if set.
+    ///
+    /// Returns `ReplicateError::InvalidRequest` if `proxy_url` is not a valid proxy url, or
+    /// the http client cannot be built with it.
+    pub fn with_proxy(mut self, proxy_url: &str) -> ReplicateResult<Self> {
+        let proxy = reqwest::Proxy::all(proxy_url).map_err(|err| {
+            ReplicateError::InvalidRequest(format!("invalid proxy url {proxy_url}: {err}"))
+        })?;
+        self.proxy = Some(proxy);
+        self.rebuild_client()
+    }
+
+    /// Replace the http client with one built from the current settings, so that a
+    /// misconfiguration fails when the config is built rather than on its first request
+    fn rebuild_client(mut self) -> ReplicateResult<Self> {
+        let client = self.build_client()?;
+        self.client = Arc::new(OnceLock::from(client));
+        Ok(self)
+    }
+
+    /// Retry requests which fail from rate limits, server errors or failed connections,
+    /// according to `retry`. By default requests are not retried.
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+
+    /// Identify requests with `user_agent` in their `User-Agent` header, so that Replicate can
+    /// attribute them to an application. Defaults to `replicate-rs/<version>`.
+    pub fn with_user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = user_agent.into();
+        self
+    }
+
+    /// Apply a function to every outgoing request, after the standard headers have been set.
+    ///
+    /// This is an escape hatch for transport customization, such as adding headers a proxy
+    /// requires.
+    pub fn with_request_hook(
+        mut self,
+        hook: impl Fn(RequestBuilder) -> RequestBuilder + Send + Sync + 'static,
+    ) -> Self {
+        self.request_hook = Some(RequestHook(Arc::new(hook)));
+        self
+    }
+
+    /// Get the http client requests are sent with. It is shared by every clone of this config,
+    /// so connections are pooled and kept alive between requests. Configs which customize the
+    /// client build it up front, and otherwise it is built on first use.
+    fn client(&self) -> ReplicateResult<reqwest::Client> {
+        if let Some(client) = self.client.get() {
+            return Ok(client.clone());
+        }
+
+        let client = self.build_client()?;
+        Ok(self.client.get_or_init(|| client).clone())
+    }
+
+    /// Build an http client from the current settings.
+    ///
+    /// Failing to build it, such as when no TLS backend can be initialized, is reported as
+    /// `ReplicateError::InvalidRequest` rather than panicking. Redirects are followed, as output
+    /// urls may redirect to signed storage urls.
+    fn build_client(&self) -> ReplicateResult<reqwest::Client> {
+        let mut builder =
+            reqwest::Client::builder().redirect(reqwest::redirect::Policy::limited(10));
+        if let Some(timeout) = self.connect_timeout {
+            builder = builder.connect_timeout(timeout);
+        }
+        if let Some(proxy) = &self.proxy {
+            builder = builder.proxy(proxy.clone());
+        }
+        builder.build().map_err(|err| {
+            ReplicateError::InvalidRequest(format!("could not build http client: {err}"))
+        })
+    }
+
+    /// Build an authorized request, with the request hook and timeout applied
+    pub(crate) fn request(
+        &self,
+        method: Method,
+        url: impl IntoUrl,
+    ) -> ReplicateResult<RequestBuilder> {
+        let request = self.stream_request(method, url)?;
+        Ok(match self.timeout {
+            Some(timeout) => request.timeout(timeout),
+            None => request,
+        })
+    }
+
+    /// Build an authorized request with the request hook applied, but no timeout, for
+    /// streams which stay open for as long as a prediction runs
+    pub(crate) fn stream_request(
+        &self,
+        method: Method,
+        url: impl IntoUrl,
+    ) -> ReplicateResult<RequestBuilder> {
+        let api_key = self.get_api_key()?;
+        let request = self
+            .client()?
+            .request(method, url)
+            .header(USER_AGENT, &self.user_agent);
+        let request = match &self.auth_scheme {
+            AuthScheme::Token => request.header("Authorization", format!("Token {api_key}")),
+            AuthScheme::Bearer => request.header("Authorization", format!("Bearer {api_key}")),
+            AuthScheme::Header(header) => request.header(header.as_str(), api_key),
+        };
+
+        Ok(match &self.request_hook {
+            Some(RequestHook(hook)) => hook(request),
+            None => request,
+        })
+    }
+
+    /// Download the content of a url returned by Replicate, such as a prediction output,
+    /// enforcing the configured size limit.
+    ///
+    /// No credentials are sent, as these urls are signed. An expired or missing url is reported
+    /// as `ReplicateError::NotFound` rather than returning the storage provider's error page.
+    pub(crate) async fn download(&self, url: &str) -> ReplicateResult<Vec<u8>> {
+        let client = self.client()?;
+        let mut request = client.get(url).header(USER_AGENT, &self.user_agent);
+        if let Some(timeout) = self.timeout {
+            request = request.timeout(timeout);
+        }
+        let request = request.build().map_err(ReplicateError::from)?;
+        let method = request.method().clone();
+        let path = request.url().path().to_string();
+        let context = |err: ReplicateError| err.with_context(&method, &path);
+
+        let response = client
+            .execute(request)
+            .await
+            .map_err(|err| context(ReplicateError::from(err)))?;
+
+        let status = response.status();
+        if matches!(
+            status,
+            StatusCode::FORBIDDEN | StatusCode::NOT_FOUND | StatusCode::GONE
+        ) {
+            return Err(context(ReplicateError::NotFound(format!(
+                "{url} has expired or does not exist ({status})"
+            ))));
+        }
+
+        let headers = response.headers().clone();
+        let data = read_bytes(response, self.max_response_size)
+            .await
+            .map_err(context)?;
+        if status.is_success() {
+            Ok(data)
+        } else {
+            Err(context(get_error(
+                status,
+                &headers,
+                &String::from_utf8_lossy(&data),
+            )))
+        }
+    }
+
+    /// Read a response body as text, enforcing the configured size limit
+    pub(crate) async fn read_body(&self, response: Response) -> ReplicateResult<String> {
+        read_body(response, self.max_response_size).await
+    }
+
+    /// Send a request and read its body, enforcing the configured size limit
+    pub(crate) async fn send(&self, request: RequestBuilder) -> ReplicateResult<String> {
+        send(request, self.max_response_size, self.retry.as_ref()).await
+    }
+
+    pub(crate) fn retry_policy(&self) -> Option<&RetryPolicy> {
+        self.retry.as_ref()
+    }
+
+    #[cfg(feature = "tokio")]
+    pub(crate) fn stream_buffer(&self) -> usize {
+        self.stream_buffer
+    }
+
+    pub(crate) fn insecure_streams_allowed(&self) -> bool {
+        self.insecure_streams
+    }
+
+    pub(crate) fn version_lookup_enabled(&self) -> bool {
+        self.version_lookup
+    }
+
+    pub(crate) fn version_lookup_ttl(&self) -> Option<Duration> {
+        self.version_lookup_ttl
+    }
+
+    /// Join an endpoint path onto the base url, so that base urls with extra path
+    /// segments or trailing slashes resolve to a single well-formed url
+    pub(crate) fn endpoint(&self, path: &str) -> String {
+        join_url(&self.base_url, path)
+    }
+}
+
+/// Send a request and read its body as text, failing on any unsuccessful status.
+///
+/// Errors are wrapped with the method and path of the request, so that the call which failed
+/// can be identified. If `retry` is set, the request is retried as described by `RetryPolicy`.
+pub(crate) async fn send(
+    request: RequestBuilder,
+    limit: Option<usize>,
+    retry: Option<&RetryPolicy>,
+) -> ReplicateResult<String> {
+    let (client, request) = request.build_split();
+    let mut request = request.map_err(ReplicateError::from)?;
+    let method = request.method().clone();
+    let path = request.url().path().to_string();
+    let context = |err: ReplicateError| err.with_context(&method, &path);
+
+    let mut attempts = 1;
+    let response = loop {
+        // Requests with a streaming body cannot be cloned, and so are never retried
+        let retry = retry
+            .filter(|retry| attempts < retry.max_attempts)
+            .and_then(|retry| Some((retry, request.try_clone()?)));
+
+        let (delay, next_request) = match (client.execute(request).await, retry) {
+            (Ok(response), Some((retry, next_request)))
+                if method == Method::GET && is_retryable(response.status()) =>
+            {
+                let delay = (response.status() == StatusCode::TOO_MANY_REQUESTS)
+                    .then(|| retry_after(response.headers()))
+                    .flatten()
+                    .unwrap_or_else(|| retry.delay(attempts));
+                (delay, next_request)
+            }
+            (Ok(response), _) => break response,
+            (Err(err), Some((retry, next_request))) if err.is_connect() => {
+                (retry.delay(attempts), next_request)
+            }
+            (Err(err), _) => return Err(context(ReplicateError::from(err))),
+        };
+        Delay::new(delay).await;
+        request = next_request;
+        attempts += 1;
+    };
+
+    let status = response.status();
+    let headers = response.headers().clone();
+    let data = read_body(response, limit).await.map_err(context)?;
+    if status.is_success() {
+        Ok(data)
+    } else {
+        Err(context(get_error(status, &headers, data.as_str())))
+    }
+}
+
+/// Read a response body as text, failing once it exceeds `limit` bytes
+pub(crate) async fn read_body(response: Response, limit: Option<usize>) -> ReplicateResult<String> {
+    if limit.is_none() {
+        return response.text().await.map_err(ReplicateError::from);
+    }
+
+    let body = read_bytes(response, limit).await?;
+    Ok(String::from_utf8_lossy(&body).into_owned())
+}
+
+/// Read a response body as bytes, failing once it exceeds `limit` bytes
+async fn read_bytes(mut response: Response, limit: Option<usize>) -> ReplicateResult<Vec<u8>> {
+    let Some(limit) = limit else {
+        return response
+            .bytes()
+            .await
+            .map(|body| body.to_vec())
+            .map_err(ReplicateError::from);
+    };
+
+    let too_large = || {
+        ReplicateError::PayloadTooLarge(format!("response body exceeded the {limit} byte limit"))
+    };
+    if response
+        .content_length()
+        .is_some_and(|length| length > limit as u64)
+    {
+        return Err(too_large());
+    }
+
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await.map_err(ReplicateError::from)? {
+        if body.len() + chunk.len() > limit {
+            return Err(too_large());
+        }
+        body.extend_from_slice(&chunk);
+    }
+
+    Ok(body)
+}
+
+/// Whether a response status is worth retrying an idempotent request for
+fn is_retryable(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+fn join_url(base_url: &str, path: &str) -> String {
+    format!(
+        "{}/{}",
+        base_url.trim_end_matches('/'),
+        path.trim_start_matches('/')
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use httpmock::prelude::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_validated() {
+        let server = MockServer::start();
+
+        let account_mock = server.mock(|when, then| {
+            when.method(GET)
+                .path("/account")
+                .header("Authorization", "Token test-api-key");
+            then.status(200).json_body_obj(&json!({
+                "type": "user",
+                "username": "replicate",
+                "name": "Replicate",
+                "github_url": null
+            }));
+        });
+
+        let config = ReplicateConfig::test(server.base_url()).unwrap();
+        config.validated().await.unwrap();
+        account_mock.assert();
+    }
+
+    #[tokio::test]
+    async fn test_validated_invalid_credentials() {
+        let server = MockServer::start();
+
+        server.mock(|when, then| {
+            when.method(GET).path("/account");
+            then.status(401).json_body_obj(&json!({
+                "title": "Unauthenticated",
+                "detail": "You did not pass a valid authentication token"
+            }));
+        });
+
+        let config = ReplicateConfig::test(server.base_url()).unwrap();
+        let err = config.validated().await.unwrap_err();
+        assert!(matches!(err.inner(), ReplicateError::InvalidCredentials(_)));
+        assert_eq!(err.context(), Some(("GET", "/account")));
+    }
+
+    #[tokio::test]
+    async fn test_auth_scheme() {
+        let server = MockServer::start();
+
+        let bearer_mock = server.mock(|when, then| {
+            when.method(GET)
+                .path("/bearer")
+                .header("Authorization", "Bearer test-api-key");
+            then.status(200);
+        });
+        let header_mock = server.mock(|when, then| {
+            when.method(GET)
+                .path("/header")
+                .header("X-Api-Key", "test-api-key");
+            then.status(200);
+        });
+
+        let config = ReplicateConfig::test(server.base_url())
+            .unwrap()
+            .with_auth_scheme(AuthScheme::Bearer);
+        config
+            .request(Method::GET, config.endpoint("bearer"))
+            .unwrap()
+            .send()
+            .await
+            .unwrap();
+
+        let config = config.with_auth_scheme(AuthScheme::Header("X-Api-Key".to_string()));
+        config
+            .request(Method::GET, config.endpoint("header"))
+            .unwrap()
+            .send()
+            .await
+            .unwrap();
+
+        bearer_mock.assert();
+        header_mock.assert();
+    }
+
+    #[tokio::test]
+    async fn test_from_token() {
+        let server = MockServer::start();
+
+        let token_mock = server.mock(|when, then| {
+            when.method(GET)
+                .path("/account")
+                .header("Authorization", "Token r8_runtime-token");
+            then.status(200);
+        });
+
+        let token = String::from("r8_runtime-token");
+        let config = ReplicateConfig {
+            base_url: server.base_url(),
+            ..ReplicateConfig::from_token(token)
+        };
+        config
+            .send(
+                config
+                    .request(Method::GET, config.endpoint("account"))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        token_mock.assert();
+    }
+
+    #[tokio::test]
+    async fn test_user_agent() {
+        let server = MockServer::start();
+
+        let default_mock = server.mock(|when, then| {
+            when.method(GET).path("/default").header(
+                "User-Agent",
+                format!("replicate-rs/{}", env!("CARGO_PKG_VERSION")),
+            );
+            then.status(200);
+        });
+        let custom_mock = server.mock(|when, then| {
+            when.method(GET)
+                .path("/custom")
+                .header("User-Agent", "hot-dog-app/1.2");
+            then.status(200);
+        });
+
+        let config = ReplicateConfig::test(server.base_url()).unwrap();
+        config
+            .send(
+                config
+                    .request(Method::GET, config.endpoint("default"))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        let config = config.with_user_agent("hot-dog-app/1.2");
+        config
+            .send(
+                config
+                    .request(Method::GET, config.endpoint("custom"))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        default_mock.assert();
+        custom_mock.assert();
+    }
+
+    #[test]
+    fn test_with_base_url() {
+        let config = ReplicateConfig::test("http://localhost".to_string())
+            .unwrap()
+            .with_base_url("https://gateway.example.com/replicate/v1/")
+            .unwrap();
+        assert_eq!(
+            config.endpoint("predictions"),
+            "https://gateway.example.com/replicate/v1/predictions"
+        );
+
+        let config = ReplicateConfig::test("http://localhost".to_string()).unwrap();
+        for base_url in ["api.replicate.com/v1", "ftp://example.com", ""] {
+            let err = config.clone().with_base_url(base_url).unwrap_err();
+            assert!(matches!(err, ReplicateError::InvalidRequest(_)));
+        }
+    }
+
+    #[test]
+    fn test_shared_client() {
+        let config = ReplicateConfig::test("http://localhost".to_string()).unwrap();
+        let cloned = config.clone().with_max_response_size(1024);
+        assert!(cloned.client.get().is_none());
+
+        config.client().unwrap();
+        assert!(Arc::ptr_eq(&config.client, &cloned.client));
+        assert!(cloned.client.get().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_retry_get() {
+        let server = MockServer::start();
+
+        let limited_mock = server.mock(|when, then| {
+            when.method(GET).path("/models");
+            then.status(429).header("Retry-After", "0");
+        });
+
+        let config = ReplicateConfig::test(server.base_url())
+            .unwrap()
+            .with_retry(RetryPolicy {
+                max_attempts: 3,
+                base_delay: Duration::from_millis(1),
+            });
+        let request = config
+            .request(Method::GET, config.endpoint("models"))
+            .unwrap();
+        let err = config.send(request).await.unwrap_err();
+        assert_eq!(err.context(), Some(("GET", "/models")));
+        limited_mock.assert_hits(3);
+    }
+
+    #[tokio::test]
+    async fn test_retry_skips_post_responses() {
+        let server = MockServer::start();
+
+        let unavailable_mock = server.mock(|when, then| {
+            when.method(POST).path("/predictions");
+            then.status(503);
+        });
+
+        let config = ReplicateConfig::test(server.base_url())
+            .unwrap()
+            .with_retry(RetryPolicy {
+                max_attempts: 3,
+                base_delay: Duration::from_millis(1),
+            });
+        let request = config
+            .request(Method::POST, config.endpoint("predictions"))
+            .unwrap()
+            .body("{}");
+        config.send(request).await.unwrap_err();
+        unavailable_mock.assert_hits(1);
+    }
+
+    #[test]
+    fn test_retry_delay() {
+        let retry = RetryPolicy {
+            max_attempts: 5,
+            base_delay: Duration::from_millis(100),
+        };
+
+        assert_eq!(retry.delay(1), Duration::from_millis(100));
+        assert_eq!(retry.delay(2), Duration::from_millis(200));
+        assert_eq!(retry.delay(4), Duration::from_millis(800));
+    }
+
+    #[tokio::test]
+    async fn test_timeout() {
+        let server = MockServer::start();
+
+        server.mock(|when, then| {
+            when.method(GET).path("/slow");
+            then.status(200).delay(Duration::from_secs(2));
+        });
+
+        let config = ReplicateConfig::test(server.base_url())
+            .unwrap()
+            .with_timeout(Duration::from_millis(100))
+            .with_connect_timeout(Duration::from_secs(1))
+            .unwrap();
+        let request = config
+            .request(Method::GET, config.endpoint("slow"))
+            .unwrap();
+        let err = config.send(request).await.unwrap_err();
+        assert!(matches!(err.inner(), ReplicateError::ClientError(..)));
+        assert_eq!(err.context(), Some(("GET", "/slow")));
+    }
+
+    #[tokio::test]
+    async fn test_proxy() {
+        let server = MockServer::start();
+
+        // The proxy receives the request for the real host, in absolute form
+        let proxy_mock = server.mock(|when, then| {
+            when.method(GET)
+                .path("/v1/account")
+                .header("Authorization", "Token test-api-key");
+            then.status(200)
+                .json_body_obj(&json!({"username": "alice"}));
+        });
+
+        let config = ReplicateConfig::from_token("test-api-key")
+            .with_base_url("http://api.replicate.invalid/v1")
+            .unwrap()
+            .with_proxy(&server.base_url())
+            .unwrap();
+        config
+            .send(
+                config
+                    .request(Method::GET, config.endpoint("account"))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        proxy_mock.assert();
+
+        let err = ReplicateConfig::from_token("test-api-key")
+            .with_proxy("http://bad proxy:8080")
+            .unwrap_err();
+        assert!(matches!(err, ReplicateError::InvalidRequest(_)));
+    }
+
+    #[test]
+    fn test_join_url() {
+        let expected = "https://api.replicate.com/v1/predictions";
+        assert_eq!(
+            join_url("https://api.replicate.com/v1", "predictions"),
+            expected
+        );
+        assert_eq!(
+            join_url("https://api.replicate.com/v1/", "predictions"),
+            expected
+        );
+        assert_eq!(
+            join_url("https://api.replicate.com/v1/", "/predictions"),
+            expected
+        );
     }
 
-    pub(crate) fn get_base_url(&self) -> String {
-        self.base_url.clone()
+    #[test]
+    fn test_join_url_with_path_prefix() {
+        assert_eq!(
+            join_url(
+                "https://proxy.internal/replicate/v1",
+                "models/replicate/hello-world"
+            ),
+            "https://proxy.internal/replicate/v1/models/replicate/hello-world"
+        );
+        assert_eq!(
+            join_url("https://proxy.internal/replicate/v1//", "models"),
+            "https://proxy.internal/replicate/v1/models"
+        );
+        assert_eq!(
+            join_url("https://proxy.internal", "predictions/1234/cancel"),
+            "https://proxy.internal/predictions/1234/cancel"
+        );
     }
 }
@@ -0,0 +1,528 @@
+//! Utilities for interacting with all deployment endpoints.
+//!
+//! This includes the following:
+//! - [Get Deployment](https://replicate.com/docs/reference/http#deployments.get)
+//! - [List Deployments](https://replicate.com/docs/reference/http#deployments.list)
+//! - [Create Deployment](https://replicate.com/docs/reference/http#deployments.create)
+//! - [Update Deployment](https://replicate.com/docs/reference/http#deployments.update)
+//! - [Delete Deployment](https://replicate.com/docs/reference/http#deployments.delete)
+//! - [Create Deployment Prediction](https://replicate.com/docs/reference/http#deployments.predictions.create)
+//!
+use crate::account::Account;
+use crate::config::ReplicateConfig;
+use crate::errors::{ReplicateError, ReplicateResult};
+use crate::hardware::HardwareSku;
+use crate::predictions::{Prediction, PredictionClient, PredictionStatus};
+
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Hardware and scaling of a deployment release
+#[derive(Debug, Clone, Deserialize)]
+pub struct DeploymentConfiguration {
+    /// Hardware the deployment runs on
+    pub hardware: HardwareSku,
+    /// Minimum number of instances kept running
+    pub min_instances: u32,
+    /// Maximum number of instances scaled up to
+    pub max_instances: u32,
+}
+
+/// A release of a deployment, created whenever its version or configuration changes
+#[derive(Debug, Clone, Deserialize)]
+pub struct DeploymentRelease {
+    /// Release number, incremented with each release
+    pub number: u64,
+    /// The `owner/name` of the model being deployed
+    pub model: String,
+    /// Specific version being deployed
+    pub version: String,
+    /// The created time for the release
+    pub created_at: String,
+    /// The account which created the release
+    #[serde(default)]
+    pub created_by: Option<Account>,
+    /// Hardware and scaling of the release
+    pub configuration: DeploymentConfiguration,
+}
+
+/// Details for a specific deployment
+#[derive(Debug, Clone, Deserialize)]
+pub struct Deployment {
+    /// Owner of the deployment
+    pub owner: String,
+    /// Name of the deployment
+    pub name: String,
+    /// The release currently serving predictions
+    pub current_release: Option<DeploymentRelease>,
+}
+
+/// Paginated list of available deployments
+#[derive(Debug, Deserialize)]
+pub struct Deployments {
+    /// Place in pagination
+    pub next: Option<String>,
+    /// Place in pagination
+    pub previous: Option<String>,
+    /// List of deployments, which is empty if none have been created
+    #[serde(default)]
+    pub results: Vec<Deployment>,
+}
+
+/// Details of a deployment to create with `DeploymentClient::create`
+#[derive(Debug, Clone, Serialize)]
+pub struct NewDeployment {
+    /// Name of the deployment, which is created under the authenticated account
+    pub name: String,
+    /// The `owner/name` of the model to deploy
+    pub model: String,
+    /// Specific version to deploy
+    pub version: String,
+    /// Hardware to run the deployment on
+    pub hardware: HardwareSku,
+    /// Minimum number of instances to keep running
+    pub min_instances: u32,
+    /// Maximum number of instances to scale up to
+    pub max_instances: u32,
+}
+
+/// Changes to a deployment made with `DeploymentClient::update`. Fields left as `None` are
+/// unchanged.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DeploymentUpdate {
+    /// Specific version to deploy
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// Hardware to run the deployment on
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hardware: Option<HardwareSku>,
+    /// Minimum number of instances to keep running
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_instances: Option<u32>,
+    /// Maximum number of instances to scale up to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_instances: Option<u32>,
+}
+
+#[derive(Serialize)]
+struct DeploymentPredictionInput {
+    input: Value,
+    stream: bool,
+}
+
+/// A client for interacting with 'deployments' endpoint
+#[derive(Debug)]
+pub struct DeploymentClient {
+    config: ReplicateConfig,
+}
+
+impl DeploymentClient {
+    /// Create a new `DeploymentClient` based upon a `ReplicateConfig` object
+    pub fn from(config: ReplicateConfig) -> Self {
+        DeploymentClient { config }
+    }
+
+    /// Get details for an existing deployment
+    pub async fn get(&self, owner: &str, name: &str) -> ReplicateResult<Deployment> {
+        let endpoint = self.config.endpoint(&format!("deployments/{owner}/{name}"));
+        let data = self
+            .config
+            .send(self.config.request(Method::GET, endpoint)?)
+            .await?;
+
+        serde_json::from_str(&data).map_err(ReplicateError::from)
+    }
+
+    /// List all deployments for the current user
+    pub async fn list(&self) -> ReplicateResult<Deployments> {
+        let endpoint = self.config.endpoint("deployments");
+        let data = self
+            .config
+            .send(self.config.request(Method::GET, endpoint)?)
+            .await?;
+
+        serde_json::from_str(&data).map_err(ReplicateError::from)
+    }
+
+    /// Create a new deployment
+    pub async fn create(&self, deployment: &NewDeployment) -> ReplicateResult<Deployment> {
+        let endpoint = self.config.endpoint("deployments");
+        let body = serde_json::to_string(deployment).map_err(ReplicateError::from)?;
+        let data = self
+            .config
+            .send(self.config.request(Method::POST, endpoint)?.body(body))
+            .await?;
+
+        serde_json::from_str(&data).map_err(ReplicateError::from)
+    }
+
+    /// Update an existing deployment, creating a new release
+    pub async fn update(
+        &self,
+        owner: &str,
+        name: &str,
+        update: &DeploymentUpdate,
+    ) -> ReplicateResult<Deployment> {
+        let endpoint = self.config.endpoint(&format!("deployments/{owner}/{name}"));
+        let body = serde_json::to_string(update).map_err(ReplicateError::from)?;
+        let data = self
+            .config
+            .send(self.config.request(Method::PATCH, endpoint)?.body(body))
+            .await?;
+
+        serde_json::from_str(&data).map_err(ReplicateError::from)
+    }
+
+    /// Delete an existing deployment
+    pub async fn delete(&self, owner: &str, name: &str) -> ReplicateResult<()> {
+        let endpoint = self.config.endpoint(&format!("deployments/{owner}/{name}"));
+        self.config
+            .send(self.config.request(Method::DELETE, endpoint)?)
+            .await?;
+        Ok(())
+    }
+
+    /// Approximately count a deployment's recent predictions by status, such as to compare how
+    /// many failed and succeeded.
+    ///
+    /// Replicate neither lists a deployment's predictions nor says which deployment a
+    /// prediction was made through, so this walks the account's predictions, newest first, and
+    /// counts those on the version of the deployment's current release. The counts are only an
+    /// estimate: predictions made directly against that version, or through another deployment
+    /// of it, are counted too, and those from the deployment's earlier releases are not. If
+    /// `max_pages` is set, at most that many pages are walked. A deployment with no release has
+    /// no predictions to count.
+    pub async fn approximate_prediction_status_counts(
+        &self,
+        owner: &str,
+        name: &str,
+        max_pages: Option<usize>,
+    ) -> ReplicateResult<HashMap<PredictionStatus, usize>> {
+        let mut counts = HashMap::new();
+        let Some(release) = self.get(owner, name).await?.current_release else {
+            return Ok(counts);
+        };
+
+        let predictions = PredictionClient::from(self.config.clone())
+            .list_all(max_pages)
+            .await?;
+        for prediction in predictions {
+            if prediction.version == release.version {
+                *counts.entry(prediction.status).or_insert(0) += 1;
+            }
+        }
+        Ok(counts)
+    }
+
+    /// Create a new prediction against a deployment, which runs on the deployment's current
+    /// release
+    pub async fn create_prediction(
+        &self,
+        owner: &str,
+        name: &str,
+        input: Value,
+        stream: bool,
+    ) -> ReplicateResult<Prediction> {
+        let endpoint = self
+            .config
+            .endpoint(&format!("deployments/{owner}/{name}/predictions"));
+        let body = serde_json::to_string(&DeploymentPredictionInput { input, stream })
+            .map_err(ReplicateError::from)?;
+        let data = self
+            .config
+            .send(self.config.request(Method::POST, endpoint)?.body(body))
+            .await?;
+
+        let mut prediction: Prediction =
+            serde_json::from_str(&data).map_err(ReplicateError::from)?;
+        prediction.config = Some(self.config.clone());
+        Ok(prediction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::predictions::PredictionStatus;
+    use httpmock::prelude::*;
+    use serde_json::json;
+
+    fn deployment(hardware: &str, min_instances: u32) -> Value {
+        json!({
+            "owner": "acme",
+            "name": "image-upscaler",
+            "current_release": {
+                "number": 1,
+                "model": "acme/esrgan",
+                "version": "5c7d5dc6dd8bf75c1acaa8565735e7986bc5b66206b55cca93cb72c9bf15ccaa",
+                "created_at": "2024-02-15T16:32:57.018467Z",
+                "created_by": {
+                    "type": "organization",
+                    "username": "acme",
+                    "name": "Acme Corp, Inc.",
+                    "github_url": "https://github.com/acme"
+                },
+                "configuration": {
+                    "hardware": hardware,
+                    "min_instances": min_instances,
+                    "max_instances": 5
+                }
+            }
+        })
+    }
+
+    #[tokio::test]
+    async fn test_get() {
+        let server = MockServer::start();
+
+        let deployment_mock = server.mock(|when, then| {
+            when.method(GET).path("/deployments/acme/image-upscaler");
+            then.status(200).json_body_obj(&deployment("gpu-t4", 1));
+        });
+
+        let config = ReplicateConfig::test(server.base_url()).unwrap();
+        let deployment_client = DeploymentClient::from(config);
+        let deployment = deployment_client
+            .get("acme", "image-upscaler")
+            .await
+            .unwrap();
+
+        let release = deployment.current_release.unwrap();
+        assert_eq!(release.configuration.hardware, HardwareSku::GpuT4);
+        assert_eq!(release.created_by.unwrap().username, "acme");
+        deployment_mock.assert();
+    }
+
+    #[tokio::test]
+    async fn test_list() {
+        let server = MockServer::start();
+
+        let deployment_mock = server.mock(|when, then| {
+            when.method(GET).path("/deployments");
+            then.status(200).json_body_obj(&json!({
+                "next": null,
+                "previous": null,
+                "results": [deployment("gpu-t4", 1)]
+            }));
+        });
+
+        let config = ReplicateConfig::test(server.base_url()).unwrap();
+        let deployment_client = DeploymentClient::from(config);
+        let deployments = deployment_client.list().await.unwrap();
+
+        assert_eq!(deployments.results.len(), 1);
+        deployment_mock.assert();
+    }
+
+    #[tokio::test]
+    async fn test_create_and_update() {
+        let server = MockServer::start();
+
+        let create_mock = server.mock(|when, then| {
+            when.method(POST).path("/deployments").json_body(json!({
+                "name": "image-upscaler",
+                "model": "acme/esrgan",
+                "version": "5c7d5dc6dd8bf75c1acaa8565735e7986bc5b66206b55cca93cb72c9bf15ccaa",
+                "hardware": "gpu-t4",
+                "min_instances": 1,
+                "max_instances": 5
+            }));
+            then.status(200).json_body_obj(&deployment("gpu-t4", 1));
+        });
+        let update_mock = server.mock(|when, then| {
+            when.method("PATCH")
+                .path("/deployments/acme/image-upscaler")
+                .json_body(json!({
+                    "hardware": "gpu-a40-large",
+                    "min_instances": 2
+                }));
+            then.status(200)
+                .json_body_obj(&deployment("gpu-a40-large", 2));
+        });
+
+        let config = ReplicateConfig::test(server.base_url()).unwrap();
+        let deployment_client = DeploymentClient::from(config);
+        deployment_client
+            .create(&NewDeployment {
+                name: "image-upscaler".to_string(),
+                model: "acme/esrgan".to_string(),
+                version: "5c7d5dc6dd8bf75c1acaa8565735e7986bc5b66206b55cca93cb72c9bf15ccaa"
+                    .to_string(),
+                hardware: HardwareSku::GpuT4,
+                min_instances: 1,
+                max_instances: 5,
+            })
+            .await
+            .unwrap();
+
+        let deployment = deployment_client
+            .update(
+                "acme",
+                "image-upscaler",
+                &DeploymentUpdate {
+                    hardware: Some(HardwareSku::GpuA40Large),
+                    min_instances: Some(2),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+
+        let configuration = deployment.current_release.unwrap().configuration;
+        assert_eq!(configuration.hardware, HardwareSku::GpuA40Large);
+        assert_eq!(configuration.min_instances, 2);
+        create_mock.assert();
+        update_mock.assert();
+    }
+
+    #[tokio::test]
+    async fn test_delete() {
+        let server = MockServer::start();
+
+        let delete_mock = server.mock(|when, then| {
+            when.method(DELETE).path("/deployments/acme/image-upscaler");
+            then.status(204);
+        });
+
+        let config = ReplicateConfig::test(server.base_url()).unwrap();
+        let deployment_client = DeploymentClient::from(config);
+        deployment_client
+            .delete("acme", "image-upscaler")
+            .await
+            .unwrap();
+
+        delete_mock.assert();
+    }
+
+    #[tokio::test]
+    async fn test_create_prediction() {
+        let server = MockServer::start();
+
+        let prediction_mock = server.mock(|when, then| {
+            when.method(POST)
+                .path("/deployments/acme/image-upscaler/predictions")
+                .json_body(json!({
+                    "input": {"image": "https://example.com/image.png"},
+                    "stream": false
+                }));
+            then.status(201).json_body_obj(&json!({
+                "id": "gm3qorzdhgbfurvjtvhg6dckhu",
+                "model": "acme/esrgan",
+                "version": "5c7d5dc6dd8bf75c1acaa8565735e7986bc5b66206b55cca93cb72c9bf15ccaa",
+                "input": {
+                    "image": "https://example.com/image.png"
+                },
+                "status": "starting",
+                "created_at": "2023-09-08T16:19:34.765994657Z",
+                "urls": {
+                    "cancel": format!("{}/predictions/gm3qorzdhgbfurvjtvhg6dckhu/cancel", server.base_url()),
+                    "get": format!("{}/predictions/gm3qorzdhgbfurvjtvhg6dckhu", server.base_url())
+                }
+            }));
+        });
+        let get_mock = server.mock(|when, then| {
+            when.method(GET)
+                .path("/predictions/gm3qorzdhgbfurvjtvhg6dckhu")
+                .header("Authorization", "Token test-api-key");
+            then.status(200).json_body_obj(&json!({
+                "id": "gm3qorzdhgbfurvjtvhg6dckhu",
+                "model": "acme/esrgan",
+                "version": "5c7d5dc6dd8bf75c1acaa8565735e7986bc5b66206b55cca93cb72c9bf15ccaa",
+                "input": {
+                    "image": "https://example.com/image.png"
+                },
+                "status": "succeeded",
+                "created_at": "2023-09-08T16:19:34.765994657Z",
+                "urls": {
+                    "cancel": format!("{}/predictions/gm3qorzdhgbfurvjtvhg6dckhu/cancel", server.base_url()),
+                    "get": format!("{}/predictions/gm3qorzdhgbfurvjtvhg6dckhu", server.base_url())
+                }
+            }));
+        });
+
+        let config = ReplicateConfig::test(server.base_url()).unwrap();
+        let deployment_client = DeploymentClient::from(config);
+        let mut prediction = deployment_client
+            .create_prediction(
+                "acme",
+                "image-upscaler",
+                json!({"image": "https://example.com/image.png"}),
+                false,
+            )
+            .await
+            .unwrap();
+        assert_eq!(prediction.status, PredictionStatus::Starting);
+
+        // The prediction reloads with the deployment client's config
+        prediction.reload().await.unwrap();
+        assert_eq!(prediction.status, PredictionStatus::Succeeded);
+
+        prediction_mock.assert();
+        get_mock.assert();
+    }
+
+    #[tokio::test]
+    async fn test_approximate_prediction_status_counts() {
+        let server = MockServer::start();
+
+        let prediction = |id: &str, version: &str, status: &str| {
+            json!({
+                "id": id,
+                "model": "acme/esrgan",
+                "version": version,
+                "input": {
+                    "image": "https://example.com/image.png"
+                },
+                "status": status,
+                "created_at": "2023-09-08T16:19:34.765994657Z",
+                "urls": {
+                    "cancel": format!("{}/predictions/{id}/cancel", server.base_url()),
+                    "get": format!("{}/predictions/{id}", server.base_url())
+                }
+            })
+        };
+        let deployed = "5c7d5dc6dd8bf75c1acaa8565735e7986bc5b66206b55cca93cb72c9bf15ccaa";
+
+        server.mock(|when, then| {
+            when.method(GET).path("/deployments/acme/image-upscaler");
+            then.status(200).json_body_obj(&deployment("gpu-t4", 1));
+        });
+        let older_page = server.mock(|when, then| {
+            when.method(GET)
+                .path("/predictions")
+                .query_param("cursor", "older");
+            then.status(200).json_body_obj(&json!({
+                "next": null,
+                "previous": null,
+                "results": [prediction("4", deployed, "failed")]
+            }));
+        });
+        let recent_page = server.mock(|when, then| {
+            when.method(GET).path("/predictions");
+            then.status(200).json_body_obj(&json!({
+                "next": format!("{}/predictions?cursor=older", server.base_url()),
+                "previous": null,
+                "results": [
+                    prediction("1", deployed, "succeeded"),
+                    prediction("2", deployed, "failed"),
+                    prediction("3", deployed, "succeeded"),
+                    prediction("5", "other-version", "failed")
+                ]
+            }));
+        });
+
+        let config = ReplicateConfig::test(server.base_url()).unwrap();
+        let counts = DeploymentClient::from(config)
+            .approximate_prediction_status_counts("acme", "image-upscaler", Some(1))
+            .await
+            .unwrap();
+
+        assert_eq!(counts.get(&PredictionStatus::Succeeded), Some(&2));
+        assert_eq!(counts.get(&PredictionStatus::Failed), Some(&1));
+        assert_eq!(counts.len(), 2);
+        recent_page.assert();
+        older_page.assert_hits(0);
+    }
+}
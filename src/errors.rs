@@ -1,32 +1,236 @@
+//! Error types returned by Replicate clients.
+//!
+use reqwest::header::{HeaderMap, RETRY_AFTER};
 use reqwest::StatusCode;
 use serde::Deserialize;
 use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use thiserror::Error;
 
+/// Errors which can be returned while interacting with Replicate
+///
+/// Errors from requests are wrapped in `WithContext` with the method and path of the request,
+/// so call `inner()` before matching on the underlying variant.
 #[derive(Debug, Clone, Error)]
 pub enum ReplicateError {
+    /// No API token was available
     MissingCredentials(String),
+    /// The API token provided was rejected by Replicate
     InvalidCredentials(String),
+    /// The account requires payment details before continuing
     PaymentNeeded(String),
-    SerializationError(String),
-    ClientError(String),
+    /// A request or response body could not be (de)serialized, with the underlying serde error
+    /// where there was one
+    SerializationError(String, #[source] Option<Arc<serde_json::Error>>),
+    /// The underlying http client failed to send or receive a request, with the underlying
+    /// reqwest error where there was one
+    ClientError(String, #[source] Option<Arc<reqwest::Error>>),
+    /// The request was invalid
     InvalidRequest(String),
+    /// Replicate rejected the request body, such as a prediction input which does not match the
+    /// model's schema
+    ValidationError {
+        /// The error returned by Replicate
+        detail: String,
+        /// Paths of the fields which failed validation, such as `input.width`
+        invalid_fields: Vec<String>,
+    },
+    /// The prediction's output could not be streamed
+    StreamNotAvailable(String),
+    /// A response body exceeded the configured size limit
+    PayloadTooLarge(String),
+    /// The requested resource does not exist, such as a model with no versions
+    NotFound(String),
+    /// The prediction has no output yet
+    OutputNotAvailable(String),
+    /// Replicate is rate limiting requests from this account
+    RateLimited {
+        /// How long Replicate asked to wait before retrying, from the `Retry-After` header
+        retry_after: Option<Duration>,
+        /// The error returned by Replicate
+        message: String,
+    },
+    /// Replicate responded with an unsuccessful status not covered by another variant, such
+    /// as a `5xx` server error
+    HttpStatus(u16, String),
+    /// Any other error
     Misc(String),
+    /// An error from a request to Replicate, with the method and path of that request
+    WithContext {
+        /// HTTP method of the failed request
+        method: String,
+        /// Path of the failed request, such as `/v1/predictions`
+        path: String,
+        /// The underlying error
+        source: Box<ReplicateError>,
+    },
+    /// Resolving a model's latest version failed, before a prediction could be created
+    VersionResolution {
+        /// The `owner/name` of the model
+        model: String,
+        /// The underlying error
+        source: Box<ReplicateError>,
+    },
 }
 
 impl fmt::Display for ReplicateError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             ReplicateError::MissingCredentials(message)
+            | ReplicateError::InvalidCredentials(message)
             | ReplicateError::PaymentNeeded(message)
-            | ReplicateError::ClientError(message)
+            | ReplicateError::ClientError(message, _)
             | ReplicateError::Misc(message)
-            | ReplicateError::SerializationError(message) => {
+            | ReplicateError::InvalidRequest(message)
+            | ReplicateError::StreamNotAvailable(message)
+            | ReplicateError::PayloadTooLarge(message)
+            | ReplicateError::NotFound(message)
+            | ReplicateError::OutputNotAvailable(message)
+            | ReplicateError::SerializationError(message, _)
+            | ReplicateError::RateLimited { message, .. }
+            | ReplicateError::ValidationError {
+                detail: message, ..
+            } => {
                 write!(f, "{message}")
             }
-            _ => {
-                write!(f, "unknown replicate error")
+            ReplicateError::HttpStatus(status, message) => {
+                write!(f, "{message} ({status})")
             }
+            ReplicateError::WithContext {
+                method,
+                path,
+                source,
+            } => {
+                write!(f, "{method} {path}: {source}")
+            }
+            ReplicateError::VersionResolution { model, source } => {
+                write!(f, "failed to resolve latest version of {model}: {source}")
+            }
+        }
+    }
+}
+
+/// A recognized cause of a failed request, parsed from the detail Replicate returned
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorReason {
+    /// The model version does not exist, or has been deleted
+    VersionNotFound,
+    /// The model does not exist, or is not visible to this account
+    ModelNotFound,
+    /// The prediction input did not match the model's schema
+    InvalidInput,
+}
+
+impl ErrorReason {
+    fn from_detail(detail: &str) -> Option<Self> {
+        let detail = detail.to_lowercase();
+        let missing = detail.contains("not found") || detail.contains("does not exist");
+
+        if detail.contains("invalid version") || (detail.contains("version") && missing) {
+            Some(ErrorReason::VersionNotFound)
+        } else if detail.contains("model") && missing {
+            Some(ErrorReason::ModelNotFound)
+        } else if detail.contains("input validation")
+            || detail.contains("invalid input")
+            || detail.contains("- input")
+        {
+            Some(ErrorReason::InvalidInput)
+        } else {
+            None
+        }
+    }
+}
+
+impl From<serde_json::Error> for ReplicateError {
+    fn from(err: serde_json::Error) -> Self {
+        ReplicateError::SerializationError(err.to_string(), Some(Arc::new(err)))
+    }
+}
+
+impl From<reqwest::Error> for ReplicateError {
+    fn from(err: reqwest::Error) -> Self {
+        ReplicateError::ClientError(err.to_string(), Some(Arc::new(err)))
+    }
+}
+
+impl ReplicateError {
+    pub(crate) fn with_context(self, method: &reqwest::Method, path: &str) -> Self {
+        ReplicateError::WithContext {
+            method: method.to_string(),
+            path: path.to_string(),
+            source: Box::new(self),
+        }
+    }
+
+    /// The underlying error, without any request context. Match on this to handle errors from
+    /// requests by their variant.
+    pub fn inner(&self) -> &ReplicateError {
+        match self {
+            ReplicateError::WithContext { source, .. }
+            | ReplicateError::VersionResolution { source, .. } => source.inner(),
+            _ => self,
+        }
+    }
+
+    /// Whether no connection could be made, in which case the request was never sent
+    pub(crate) fn is_connect(&self) -> bool {
+        matches!(self.inner(), ReplicateError::ClientError(_, Some(err)) if err.is_connect())
+    }
+
+    /// The method and path of the request which produced this error, if it came from a request
+    pub fn context(&self) -> Option<(&str, &str)> {
+        match self {
+            ReplicateError::WithContext { method, path, .. } => Some((method, path)),
+            ReplicateError::VersionResolution { source, .. } => source.context(),
+            _ => None,
+        }
+    }
+
+    /// The raw message describing this error, as returned by Replicate where available
+    pub fn detail(&self) -> &str {
+        match self {
+            ReplicateError::MissingCredentials(message)
+            | ReplicateError::InvalidCredentials(message)
+            | ReplicateError::PaymentNeeded(message)
+            | ReplicateError::SerializationError(message, _)
+            | ReplicateError::ClientError(message, _)
+            | ReplicateError::InvalidRequest(message)
+            | ReplicateError::StreamNotAvailable(message)
+            | ReplicateError::PayloadTooLarge(message)
+            | ReplicateError::NotFound(message)
+            | ReplicateError::OutputNotAvailable(message)
+            | ReplicateError::Misc(message)
+            | ReplicateError::RateLimited { message, .. }
+            | ReplicateError::HttpStatus(_, message)
+            | ReplicateError::ValidationError {
+                detail: message, ..
+            } => message,
+            ReplicateError::WithContext { source, .. }
+            | ReplicateError::VersionResolution { source, .. } => source.detail(),
+        }
+    }
+
+    /// The recognized cause of a `NotFound`, `InvalidRequest`, `ValidationError`, `HttpStatus` or
+    /// `Misc` error, if its detail matches a known pattern. The raw detail remains available
+    /// through `detail`.
+    pub fn reason(&self) -> Option<ErrorReason> {
+        match self.inner() {
+            ReplicateError::NotFound(detail)
+            | ReplicateError::InvalidRequest(detail)
+            | ReplicateError::ValidationError { detail, .. }
+            | ReplicateError::HttpStatus(_, detail)
+            | ReplicateError::Misc(detail) => ErrorReason::from_detail(detail),
+            _ => None,
+        }
+    }
+
+    /// How long Replicate asked to wait before retrying, if this error is `RateLimited` and
+    /// the response included a `Retry-After` header
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self.inner() {
+            ReplicateError::RateLimited { retry_after, .. } => *retry_after,
+            _ => None,
         }
     }
 }
@@ -35,12 +239,68 @@ impl fmt::Display for ReplicateError {
 struct ErrorData {
     title: String,
     detail: String,
+    #[serde(default)]
+    invalid_fields: Vec<InvalidField>,
+}
+
+#[derive(Deserialize)]
+struct InvalidField {
+    field: String,
+}
+
+impl ErrorData {
+    /// Paths of the fields which failed validation, taken from `invalid_fields` where Replicate
+    /// provides it, and otherwise from the `- field: reason` lines of the detail
+    fn invalid_fields(&self) -> Vec<String> {
+        let fields: Vec<&str> = if self.invalid_fields.is_empty() {
+            self.detail
+                .lines()
+                .filter_map(|line| line.trim().strip_prefix("- "))
+                .filter_map(|line| line.split_once(':'))
+                .map(|(field, _)| field.trim())
+                .collect()
+        } else {
+            self.invalid_fields
+                .iter()
+                .map(|invalid| invalid.field.as_str())
+                .collect()
+        };
+
+        let mut unique = Vec::new();
+        for field in fields {
+            if !field.is_empty() && !unique.iter().any(|seen| seen == field) {
+                unique.push(field.to_string());
+            }
+        }
+        unique
+    }
 }
 
 /// Result Alias for Replicate Output and Errors
 pub type ReplicateResult<T> = std::result::Result<T, ReplicateError>;
 
-pub(crate) fn get_error(status: reqwest::StatusCode, data: &str) -> ReplicateError {
+/// The delay requested by a `Retry-After` header, given either in seconds or as an http date.
+/// A date which has already passed is no delay.
+pub(crate) fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let retry_at = UNIX_EPOCH + Duration::from_secs(u64::try_from(date.timestamp()).ok()?);
+    Some(
+        retry_at
+            .duration_since(SystemTime::now())
+            .unwrap_or(Duration::ZERO),
+    )
+}
+
+pub(crate) fn get_error(
+    status: reqwest::StatusCode,
+    headers: &HeaderMap,
+    data: &str,
+) -> ReplicateError {
     match status {
         StatusCode::PAYMENT_REQUIRED => {
             let data: Option<ErrorData> = serde_json::from_str(data).ok();
@@ -58,14 +318,280 @@ pub(crate) fn get_error(status: reqwest::StatusCode, data: &str) -> ReplicateErr
                 ReplicateError::InvalidCredentials("error details not available".to_string())
             }
         }
+        StatusCode::NOT_FOUND => {
+            let data: Option<ErrorData> = serde_json::from_str(data).ok();
+            if let Some(data) = data {
+                ReplicateError::NotFound(format!("{}: {}", data.title, data.detail))
+            } else {
+                ReplicateError::NotFound("error details not available".to_string())
+            }
+        }
+        StatusCode::UNPROCESSABLE_ENTITY => {
+            let data: Option<ErrorData> = serde_json::from_str(data).ok();
+            if let Some(data) = data {
+                let invalid_fields = data.invalid_fields();
+                let detail = format!("{}: {}", data.title, data.detail);
+                if invalid_fields.is_empty() {
+                    ReplicateError::InvalidRequest(detail)
+                } else {
+                    ReplicateError::ValidationError {
+                        detail,
+                        invalid_fields,
+                    }
+                }
+            } else {
+                ReplicateError::InvalidRequest("error details not available".to_string())
+            }
+        }
+        StatusCode::TOO_MANY_REQUESTS => {
+            let data: Option<ErrorData> = serde_json::from_str(data).ok();
+            let message = if let Some(data) = data {
+                format!("{}: {}", data.title, data.detail)
+            } else {
+                "error details not available".to_string()
+            };
+            ReplicateError::RateLimited {
+                retry_after: retry_after(headers),
+                message,
+            }
+        }
         _ => {
-            println!("DATA: {:?}", data);
             let data: Option<ErrorData> = serde_json::from_str(data).ok();
             if let Some(data) = data {
-                ReplicateError::Misc(format!("{}: {}", data.title, data.detail))
+                ReplicateError::HttpStatus(
+                    status.as_u16(),
+                    format!("{}: {}", data.title, data.detail),
+                )
             } else {
-                ReplicateError::Misc("error details not available".to_string())
+                ReplicateError::HttpStatus(
+                    status.as_u16(),
+                    "error details not available".to_string(),
+                )
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    fn error(status: StatusCode, title: &str, detail: &str) -> ReplicateError {
+        get_error(
+            status,
+            &HeaderMap::new(),
+            &json!({"title": title, "detail": detail}).to_string(),
+        )
+    }
+
+    #[test]
+    fn test_version_not_found() {
+        let err = error(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "Invalid version",
+            "Invalid version or not permitted",
+        );
+        assert!(matches!(err, ReplicateError::InvalidRequest(_)));
+        assert_eq!(err.reason(), Some(ErrorReason::VersionNotFound));
+        assert_eq!(
+            err.detail(),
+            "Invalid version: Invalid version or not permitted"
+        );
+
+        let err = error(
+            StatusCode::NOT_FOUND,
+            "Not found",
+            "The requested version does not exist",
+        );
+        assert!(matches!(err, ReplicateError::NotFound(_)));
+        assert_eq!(err.reason(), Some(ErrorReason::VersionNotFound));
+    }
+
+    #[test]
+    fn test_model_not_found() {
+        let err = error(
+            StatusCode::NOT_FOUND,
+            "Not found",
+            "Model not found: replicate/missing",
+        );
+        assert!(matches!(err, ReplicateError::NotFound(_)));
+        assert_eq!(err.reason(), Some(ErrorReason::ModelNotFound));
+    }
+
+    #[test]
+    fn test_invalid_input() {
+        let err = error(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "Input validation failed",
+            "- input: Additional property prompt2 is not allowed",
+        );
+        assert_eq!(err.reason(), Some(ErrorReason::InvalidInput));
+        assert_eq!(
+            err.detail(),
+            "Input validation failed: - input: Additional property prompt2 is not allowed"
+        );
+    }
+
+    #[test]
+    fn test_validation_error() {
+        let err = error(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "Input validation failed",
+            "- input.width: Must be less than or equal to 1024\n- input: prompt is required\n- input.width: Must be a multiple of 8",
+        );
+        match &err {
+            ReplicateError::ValidationError { invalid_fields, .. } => {
+                assert_eq!(invalid_fields, &vec!["input.width", "input"]);
             }
+            _ => panic!("expected a validation error, got {err:?}"),
         }
+        assert_eq!(err.reason(), Some(ErrorReason::InvalidInput));
+
+        let err = get_error(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            &HeaderMap::new(),
+            &json!({
+                "title": "Input validation failed",
+                "detail": "- input: seed must be an integer",
+                "invalid_fields": [
+                    {"type": "type", "field": "input.seed", "description": "seed must be an integer"}
+                ]
+            })
+            .to_string(),
+        );
+        match err {
+            ReplicateError::ValidationError { invalid_fields, .. } => {
+                assert_eq!(invalid_fields, vec!["input.seed"]);
+            }
+            _ => panic!("expected a validation error, got {err:?}"),
+        }
+    }
+
+    #[test]
+    fn test_rate_limited() {
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, "12".parse().unwrap());
+        let err = get_error(
+            StatusCode::TOO_MANY_REQUESTS,
+            &headers,
+            &json!({"title": "Too many requests", "detail": "Request was throttled"}).to_string(),
+        )
+        .with_context(&reqwest::Method::POST, "/v1/predictions");
+
+        assert!(matches!(err.inner(), ReplicateError::RateLimited { .. }));
+        assert_eq!(err.retry_after(), Some(Duration::from_secs(12)));
+        assert_eq!(err.detail(), "Too many requests: Request was throttled");
+
+        let err = get_error(StatusCode::TOO_MANY_REQUESTS, &HeaderMap::new(), "");
+        assert!(matches!(err, ReplicateError::RateLimited { .. }));
+        assert_eq!(err.retry_after(), None);
+    }
+
+    #[test]
+    fn test_retry_after_date() {
+        let in_two_minutes = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+            + 120;
+        let date = chrono::DateTime::from_timestamp(in_two_minutes as i64, 0)
+            .unwrap()
+            .format("%a, %d %b %Y %H:%M:%S GMT")
+            .to_string();
+
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, date.parse().unwrap());
+        let delay = retry_after(&headers).unwrap();
+        assert!(delay > Duration::from_secs(100) && delay <= Duration::from_secs(120));
+
+        headers.insert(
+            RETRY_AFTER,
+            "Wed, 21 Oct 2015 07:28:00 GMT".parse().unwrap(),
+        );
+        assert_eq!(retry_after(&headers), Some(Duration::ZERO));
+
+        headers.insert(RETRY_AFTER, "soon".parse().unwrap());
+        assert_eq!(retry_after(&headers), None);
+    }
+
+    #[test]
+    fn test_http_status() {
+        let err = error(
+            StatusCode::BAD_GATEWAY,
+            "Bad gateway",
+            "The server is temporarily unavailable",
+        );
+        assert!(matches!(err, ReplicateError::HttpStatus(502, _)));
+        assert_eq!(
+            err.to_string(),
+            "Bad gateway: The server is temporarily unavailable (502)"
+        );
+    }
+
+    #[test]
+    fn test_invalid_credentials() {
+        let err = get_error(StatusCode::UNAUTHORIZED, &HeaderMap::new(), "");
+        assert!(matches!(err, ReplicateError::InvalidCredentials(_)));
+        assert_eq!(err.to_string(), "error details not available");
+    }
+
+    #[test]
+    fn test_source() {
+        use std::error::Error;
+
+        let serde_err = serde_json::from_str::<Value>("{").unwrap_err();
+        let err = ReplicateError::from(serde_err)
+            .with_context(&reqwest::Method::GET, "/v1/predictions/1234");
+
+        let source = err.source().unwrap();
+        assert_eq!(
+            source.to_string(),
+            "EOF while parsing an object at line 1 column 1"
+        );
+        let serde_err = source.source().unwrap();
+        assert_eq!(
+            serde_err.to_string(),
+            "EOF while parsing an object at line 1 column 1"
+        );
+        assert!(serde_err.source().is_none());
+
+        let err = ReplicateError::SerializationError("no results".to_string(), None);
+        assert!(err.source().is_none());
+    }
+
+    #[test]
+    fn test_with_context() {
+        let err = error(
+            StatusCode::NOT_FOUND,
+            "Not found",
+            "Model not found: replicate/missing",
+        )
+        .with_context(&reqwest::Method::GET, "/v1/models/replicate/missing");
+
+        assert_eq!(
+            err.to_string(),
+            "GET /v1/models/replicate/missing: Not found: Model not found: replicate/missing"
+        );
+        assert!(matches!(err.inner(), ReplicateError::NotFound(_)));
+        assert_eq!(err.reason(), Some(ErrorReason::ModelNotFound));
+        assert_eq!(
+            err.detail(),
+            "Not found: Model not found: replicate/missing"
+        );
+    }
+
+    #[test]
+    fn test_unrecognized_reason() {
+        let err = error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Internal server error",
+            "Something went wrong",
+        );
+        assert!(matches!(err, ReplicateError::HttpStatus(500, _)));
+        assert_eq!(err.reason(), None);
+
+        let err = ReplicateError::NotFound("error details not available".to_string());
+        assert_eq!(err.reason(), None);
     }
 }
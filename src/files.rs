@@ -0,0 +1,282 @@
+//! Utilities for interacting with all file endpoints.
+//!
+//! This includes the following:
+//! - [Create a File](https://replicate.com/docs/reference/http#files.create)
+//!
+//! Uploaded files are served from the url in `ReplicateFile::urls`, which can be passed as a
+//! prediction input in place of a hosted url.
+//!
+use crate::config::ReplicateConfig;
+use crate::errors::{ReplicateError, ReplicateResult};
+
+use futures_timer::Delay;
+use reqwest::multipart::{Form, Part};
+use reqwest::Method;
+use std::path::Path;
+
+/// Provided urls to retrieve the specific file.
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct FileUrls {
+    /// Url endpoint to retrieve the file's content
+    pub get: String,
+}
+
+/// Details for a file uploaded to Replicate
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct ReplicateFile {
+    /// Id of the file
+    pub id: String,
+    /// Name the file was uploaded with
+    pub name: String,
+    /// Content type the file was uploaded with
+    pub content_type: String,
+    /// Size of the file in bytes
+    pub size: u64,
+    /// The created time for the file
+    pub created_at: String,
+    /// The time after which the file will be deleted, if it expires
+    #[serde(default)]
+    pub expires_at: Option<String>,
+    /// Urls to retrieve the file
+    pub urls: FileUrls,
+}
+
+/// A client for interacting with 'files' endpoint
+#[derive(Debug)]
+pub struct FileClient {
+    config: ReplicateConfig,
+}
+
+impl FileClient {
+    /// Create a new `FileClient` based upon a `ReplicateConfig` object
+    pub fn from(config: ReplicateConfig) -> Self {
+        FileClient { config }
+    }
+
+    /// Upload `bytes` as a new file named `filename`
+    ///
+    /// A multipart body cannot be resent, so the form is built again for each attempt. As with
+    /// other requests which are not idempotent, an upload is only retried by the config's
+    /// `RetryPolicy` when no connection could be made.
+    pub async fn upload(
+        &self,
+        bytes: Vec<u8>,
+        filename: &str,
+        content_type: &str,
+    ) -> ReplicateResult<ReplicateFile> {
+        let endpoint = self.config.endpoint("files");
+        let mut attempts = 1;
+        let data = loop {
+            let part = Part::bytes(bytes.clone())
+                .file_name(filename.to_string())
+                .mime_str(content_type)
+                .map_err(|err| {
+                    ReplicateError::InvalidRequest(format!(
+                        "invalid content type {content_type}: {err}"
+                    ))
+                })?;
+            let form = Form::new().part("content", part);
+
+            let request = self
+                .config
+                .request(Method::POST, &endpoint)?
+                .multipart(form);
+            match (self.config.send(request).await, self.config.retry_policy()) {
+                (Err(err), Some(retry)) if err.is_connect() && attempts < retry.max_attempts => {
+                    Delay::new(retry.delay(attempts)).await;
+                    attempts += 1;
+                }
+                (result, _) => break result?,
+            }
+        };
+
+        serde_json::from_str(&data).map_err(ReplicateError::from)
+    }
+
+    /// Upload the file at `path`, named after its file name, with a content type guessed from
+    /// its extension
+    ///
+    /// The file is read into memory before it is uploaded. Without the `tokio` feature, the file
+    /// is read with blocking io.
+    pub async fn upload_path(&self, path: &Path) -> ReplicateResult<ReplicateFile> {
+        #[cfg(feature = "tokio")]
+        let bytes = tokio::fs::read(path).await;
+        #[cfg(not(feature = "tokio"))]
+        let bytes = std::fs::read(path);
+        let bytes = bytes.map_err(|err| {
+            ReplicateError::InvalidRequest(format!("could not read {}: {err}", path.display()))
+        })?;
+        let filename = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "file".to_string());
+
+        self.upload(bytes, &filename, content_type_for(path)).await
+    }
+}
+
+/// Guess the content type of a file from its extension, falling back to
+/// `application/octet-stream`
+pub(crate) fn content_type_for(path: &Path) -> &'static str {
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    match extension.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "bmp" => "image/bmp",
+        "svg" => "image/svg+xml",
+        "tif" | "tiff" => "image/tiff",
+        "mp3" => "audio/mpeg",
+        "wav" => "audio/wav",
+        "ogg" => "audio/ogg",
+        "flac" => "audio/flac",
+        "m4a" => "audio/mp4",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        "mov" => "video/quicktime",
+        "txt" => "text/plain",
+        "csv" => "text/csv",
+        "json" => "application/json",
+        "pdf" => "application/pdf",
+        "zip" => "application/zip",
+        "tar" => "application/x-tar",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::RetryPolicy;
+    use httpmock::prelude::*;
+    use serde_json::json;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn file(name: &str, content_type: &str, size: u64) -> serde_json::Value {
+        json!({
+            "id": "cneqzikepnug6xezperrr4z55o4r",
+            "name": name,
+            "content_type": content_type,
+            "size": size,
+            "etag": "f211779ff7502705bbf42e9874a17ab3",
+            "checksums": {
+                "sha256": "7282eb6991fa4f38d80c312dc207d938c156d714c94681623aedac846488e7d3"
+            },
+            "metadata": {},
+            "created_at": "2024-08-22T12:26:51.079Z",
+            "expires_at": "2024-08-23T12:26:51.079Z",
+            "urls": {
+                "get": "https://api.replicate.com/v1/files/cneqzikepnug6xezperrr4z55o4r"
+            }
+        })
+    }
+
+    #[tokio::test]
+    async fn test_upload() {
+        let server = MockServer::start();
+
+        let file_mock = server.mock(|when, then| {
+            when.method(POST)
+                .path("/files")
+                .header_exists("content-type")
+                .body_contains("name=\"content\"; filename=\"cat.png\"")
+                .body_contains("Content-Type: image/png")
+                .body_contains("not really a png");
+            then.status(201)
+                .json_body_obj(&file("cat.png", "image/png", 16));
+        });
+
+        let config = ReplicateConfig::test(server.base_url()).unwrap();
+        let file = FileClient::from(config)
+            .upload(b"not really a png".to_vec(), "cat.png", "image/png")
+            .await
+            .unwrap();
+
+        assert_eq!(file.id, "cneqzikepnug6xezperrr4z55o4r");
+        assert_eq!(
+            file.urls.get,
+            "https://api.replicate.com/v1/files/cneqzikepnug6xezperrr4z55o4r"
+        );
+        file_mock.assert();
+    }
+
+    #[tokio::test]
+    async fn test_upload_path() {
+        let server = MockServer::start();
+
+        let path = std::env::temp_dir().join("replicate-rs-test-upload-path.wav");
+        std::fs::write(&path, b"RIFF").unwrap();
+
+        let file_mock = server.mock(|when, then| {
+            when.method(POST)
+                .path("/files")
+                .body_contains("filename=\"replicate-rs-test-upload-path.wav\"")
+                .body_contains("Content-Type: audio/wav");
+            then.status(201).json_body_obj(&file(
+                "replicate-rs-test-upload-path.wav",
+                "audio/wav",
+                4,
+            ));
+        });
+
+        let config = ReplicateConfig::test(server.base_url()).unwrap();
+        let file_client = FileClient::from(config);
+        let file = file_client.upload_path(&path).await.unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(file.content_type, "audio/wav");
+        file_mock.assert();
+
+        let err = file_client
+            .upload_path(Path::new("/does/not/exist.png"))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ReplicateError::InvalidRequest(_)));
+    }
+
+    #[tokio::test]
+    async fn test_upload_retries_failed_connections() {
+        // Bind and drop a listener to find a port which refuses connections
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        drop(listener);
+
+        let attempts = Arc::new(AtomicU32::new(0));
+        let counter = attempts.clone();
+        let config = ReplicateConfig::test(base_url)
+            .unwrap()
+            .with_retry(RetryPolicy {
+                max_attempts: 3,
+                base_delay: Duration::from_millis(1),
+            })
+            .with_request_hook(move |request| {
+                counter.fetch_add(1, Ordering::SeqCst);
+                request
+            });
+
+        let err = FileClient::from(config)
+            .upload(b"not really a png".to_vec(), "cat.png", "image/png")
+            .await
+            .unwrap_err();
+
+        assert!(err.is_connect());
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn test_content_type_for() {
+        assert_eq!(content_type_for(Path::new("cat.PNG")), "image/png");
+        assert_eq!(content_type_for(Path::new("song.mp3")), "audio/mpeg");
+        assert_eq!(
+            content_type_for(Path::new("weights")),
+            "application/octet-stream"
+        );
+    }
+}
@@ -0,0 +1,211 @@
+//! Utilities for reasoning about the hardware models run on.
+//!
+//! This includes the following:
+//! - [List Hardware](https://replicate.com/docs/reference/http#hardware.list)
+//!
+use crate::config::ReplicateConfig;
+use crate::errors::{ReplicateError, ReplicateResult};
+
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Coarse relative cost of running on a hardware SKU, ordered from cheapest to most expensive.
+///
+/// Tiers are only a guide for comparing SKUs, see
+/// [Replicate's pricing](https://replicate.com/pricing) for actual prices.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CostTier {
+    /// CPU only hardware
+    Low,
+    /// Entry level GPUs
+    Medium,
+    /// Mid range GPUs
+    High,
+    /// Top end GPUs
+    Premium,
+}
+
+/// A hardware SKU which models, deployments and trainings can run on
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum HardwareSku {
+    /// `cpu`
+    Cpu,
+    /// `gpu-t4`, an Nvidia T4 GPU
+    GpuT4,
+    /// `gpu-a40-small`, an Nvidia A40 GPU
+    GpuA40Small,
+    /// `gpu-a40-large`, an Nvidia A40 (Large) GPU
+    GpuA40Large,
+    /// `gpu-l40s`, an Nvidia L40S GPU
+    GpuL40s,
+    /// `gpu-a100-large`, an Nvidia A100 (80GB) GPU
+    GpuA100Large,
+    /// `gpu-h100`, an Nvidia H100 GPU
+    GpuH100,
+    /// Any SKU not known to this crate
+    Other(String),
+}
+
+impl HardwareSku {
+    /// The SKU as used by Replicate, such as `gpu-t4`
+    pub fn as_str(&self) -> &str {
+        match self {
+            HardwareSku::Cpu => "cpu",
+            HardwareSku::GpuT4 => "gpu-t4",
+            HardwareSku::GpuA40Small => "gpu-a40-small",
+            HardwareSku::GpuA40Large => "gpu-a40-large",
+            HardwareSku::GpuL40s => "gpu-l40s",
+            HardwareSku::GpuA100Large => "gpu-a100-large",
+            HardwareSku::GpuH100 => "gpu-h100",
+            HardwareSku::Other(sku) => sku,
+        }
+    }
+
+    /// The coarse relative cost of this SKU, or `None` if the SKU is not known
+    pub fn cost_tier(&self) -> Option<CostTier> {
+        match self {
+            HardwareSku::Cpu => Some(CostTier::Low),
+            HardwareSku::GpuT4 => Some(CostTier::Medium),
+            HardwareSku::GpuA40Small | HardwareSku::GpuA40Large | HardwareSku::GpuL40s => {
+                Some(CostTier::High)
+            }
+            HardwareSku::GpuA100Large | HardwareSku::GpuH100 => Some(CostTier::Premium),
+            HardwareSku::Other(_) => None,
+        }
+    }
+}
+
+impl From<&str> for HardwareSku {
+    fn from(sku: &str) -> Self {
+        match sku {
+            "cpu" => HardwareSku::Cpu,
+            "gpu-t4" => HardwareSku::GpuT4,
+            "gpu-a40-small" => HardwareSku::GpuA40Small,
+            "gpu-a40-large" => HardwareSku::GpuA40Large,
+            "gpu-l40s" => HardwareSku::GpuL40s,
+            "gpu-a100-large" => HardwareSku::GpuA100Large,
+            "gpu-h100" => HardwareSku::GpuH100,
+            other => HardwareSku::Other(other.to_string()),
+        }
+    }
+}
+
+impl From<String> for HardwareSku {
+    fn from(sku: String) -> Self {
+        HardwareSku::from(sku.as_str())
+    }
+}
+
+impl From<HardwareSku> for String {
+    fn from(sku: HardwareSku) -> Self {
+        sku.as_str().to_string()
+    }
+}
+
+impl fmt::Display for HardwareSku {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Hardware available to run models, deployments and trainings on
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Hardware {
+    /// Human readable name, such as `Nvidia T4 GPU`
+    pub name: String,
+    /// SKU used to select the hardware, such as `gpu-t4`
+    pub sku: HardwareSku,
+}
+
+/// A client for interacting with 'hardware' endpoint
+#[derive(Debug)]
+pub struct HardwareClient {
+    config: ReplicateConfig,
+}
+
+impl HardwareClient {
+    /// Create a new `HardwareClient` based upon a `ReplicateConfig` object
+    pub fn from(config: ReplicateConfig) -> Self {
+        HardwareClient { config }
+    }
+
+    /// List all hardware currently available on Replicate. SKUs not known to this crate are
+    /// returned as `HardwareSku::Other`.
+    pub async fn list(&self) -> ReplicateResult<Vec<Hardware>> {
+        let endpoint = self.config.endpoint("hardware");
+        let data = self
+            .config
+            .send(self.config.request(Method::GET, endpoint)?)
+            .await?;
+
+        serde_json::from_str(&data).map_err(ReplicateError::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use httpmock::prelude::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_list() {
+        let server = MockServer::start();
+
+        let hardware_mock = server.mock(|when, then| {
+            when.method(GET).path("/hardware");
+            then.status(200).json_body_obj(&json!([
+                {"name": "CPU", "sku": "cpu"},
+                {"name": "Nvidia T4 GPU", "sku": "gpu-t4"},
+                {"name": "Nvidia B200 GPU", "sku": "gpu-b200"}
+            ]));
+        });
+
+        let config = ReplicateConfig::test(server.base_url()).unwrap();
+        let hardware = HardwareClient::from(config).list().await.unwrap();
+
+        assert_eq!(
+            hardware,
+            vec![
+                Hardware {
+                    name: "CPU".to_string(),
+                    sku: HardwareSku::Cpu,
+                },
+                Hardware {
+                    name: "Nvidia T4 GPU".to_string(),
+                    sku: HardwareSku::GpuT4,
+                },
+                Hardware {
+                    name: "Nvidia B200 GPU".to_string(),
+                    sku: HardwareSku::Other("gpu-b200".to_string()),
+                },
+            ]
+        );
+        hardware_mock.assert();
+    }
+
+    #[test]
+    fn test_hardware_sku() {
+        let skus: Vec<HardwareSku> =
+            serde_json::from_value(json!(["cpu", "gpu-a100-large", "gpu-b200"])).unwrap();
+
+        assert_eq!(
+            skus,
+            vec![
+                HardwareSku::Cpu,
+                HardwareSku::GpuA100Large,
+                HardwareSku::Other("gpu-b200".to_string()),
+            ]
+        );
+        assert_eq!(skus[0].cost_tier(), Some(CostTier::Low));
+        assert_eq!(skus[1].cost_tier(), Some(CostTier::Premium));
+        assert_eq!(skus[2].cost_tier(), None);
+        assert!(CostTier::Medium < CostTier::High);
+        assert_eq!(
+            serde_json::to_value(&skus).unwrap(),
+            json!(["cpu", "gpu-a100-large", "gpu-b200"])
+        );
+    }
+}
@@ -0,0 +1,86 @@
+//! Helpers for building prediction inputs.
+//!
+//! Small files can be passed inline as base64 `data:` URIs rather than hosted urls:
+//!
+//! ```rust,no_run
+//! use replicate_rs::input::data_uri_from_path;
+//! use serde_json::json;
+//!
+//! # fn main() -> std::io::Result<()> {
+//! let input = json!({"image": data_uri_from_path("cat.png")?});
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Larger files should be uploaded with `FileClient::upload` instead, and their url passed as
+//! the input.
+//!
+use crate::files::content_type_for;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use std::io;
+use std::path::Path;
+
+/// Largest file, in bytes, which `data_uri_from_path` will encode. Replicate rejects request
+/// bodies with very large data URIs, so bigger files should be uploaded with
+/// `FileClient::upload`.
+pub const MAX_DATA_URI_SIZE: u64 = 1024 * 1024;
+
+/// Encode `bytes` as a base64 `data:` URI with the given content type
+pub fn data_uri(bytes: &[u8], content_type: &str) -> String {
+    format!("data:{content_type};base64,{}", STANDARD.encode(bytes))
+}
+
+/// Read the file at `path` and encode it as a base64 `data:` URI, with a content type guessed
+/// from its extension
+///
+/// Files larger than `MAX_DATA_URI_SIZE` fail with `io::ErrorKind::InvalidInput`, rather than
+/// being encoded into a request Replicate will reject.
+pub fn data_uri_from_path(path: impl AsRef<Path>) -> io::Result<String> {
+    let path = path.as_ref();
+    let size = std::fs::metadata(path)?.len();
+    if size > MAX_DATA_URI_SIZE {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "{} is {size} bytes, larger than the {MAX_DATA_URI_SIZE} byte limit for data URIs, upload it with FileClient::upload instead",
+                path.display()
+            ),
+        ));
+    }
+
+    let bytes = std::fs::read(path)?;
+    Ok(data_uri(&bytes, content_type_for(path)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_data_uri() {
+        assert_eq!(
+            data_uri(b"hello", "text/plain"),
+            "data:text/plain;base64,aGVsbG8="
+        );
+    }
+
+    #[test]
+    fn test_data_uri_from_path() {
+        let path = std::env::temp_dir().join("replicate-rs-test-data-uri.png");
+        std::fs::write(&path, [0x89, b'P', b'N', b'G']).unwrap();
+        let uri = data_uri_from_path(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(uri, "data:image/png;base64,iVBORw==");
+
+        let path = std::env::temp_dir().join("replicate-rs-test-data-uri-large.bin");
+        std::fs::write(&path, vec![0; MAX_DATA_URI_SIZE as usize + 1]).unwrap();
+        let err = data_uri_from_path(&path).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+        let err = data_uri_from_path("/does/not/exist.png").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    }
+}
@@ -11,6 +11,23 @@
 //!
 //! # Examples
 //!
+//! #### Set up a Client
+//!
+//! `Replicate` hands out a client for each group of endpoints, all sharing one config and
+//! connection pool. The clients can also be created individually from a `ReplicateConfig`.
+//!
+//! ```rust
+//! use replicate_rs::Replicate;
+//!
+//! // Reads the api token from the REPLICATE_API_KEY environment variable
+//! let replicate = Replicate::new();
+//!
+//! // Or authenticate with a token obtained at runtime
+//! let replicate = Replicate::from_token("r8_...");
+//! let prediction_client = replicate.predictions();
+//! let model_client = replicate.models();
+//! ```
+//!
 //! #### Create a Prediction
 //!
 //! Create a prediction, and get refreshed prediction data.
@@ -48,10 +65,23 @@
 
 #![warn(missing_docs)]
 
+pub mod account;
+pub mod client;
+pub mod collections;
 pub mod config;
+pub mod deployments;
 pub mod errors;
+pub mod files;
+pub mod hardware;
+pub mod input;
 pub mod models;
+mod pagination;
 pub mod predictions;
+pub mod prelude;
+pub mod schema;
+pub mod trainings;
+
+pub use crate::client::Replicate;
 
 use crate::errors::{ReplicateError, ReplicateResult};
 use std::env::var;
@@ -1,23 +1,26 @@
 //! Utilities for interacting with models endpoints.
 //!
 //! This includes the following:
+//! - [Create a Model](https://replicate.com/docs/reference/http#models.create)
 //! - [Get a Model](https://replicate.com/docs/reference/http#models.get)
 //! - [Get a Model Version](https://replicate.com/docs/reference/http#models.versions.get)
 //! - [List a Model's Versions](https://replicate.com/docs/reference/http#models.versions.list)
 //! - [List all Public Models](https://replicate.com/docs/reference/http#models.list)
+//! - [Search Public Models](https://replicate.com/docs/reference/http#models.search)
 //!
-use anyhow::anyhow;
-use reqwest::StatusCode;
-use serde::Deserialize;
+use futures::stream::{self, StreamExt};
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 use crate::config::ReplicateConfig;
 use crate::errors::{get_error, ReplicateError, ReplicateResult};
-
-#[derive(Debug, Deserialize)]
-struct ModelVersionError {
-    detail: String,
-}
+use crate::hardware::HardwareSku;
+use crate::pagination::{collect_pages, page_url, Page};
+use crate::schema::{input_json_schema, parse_input_schema, InputSchema};
 
 /// Version details for a particular model
 #[derive(Debug, Deserialize, Clone)]
@@ -32,6 +35,33 @@ pub struct ModelVersion {
     pub openapi_schema: serde_json::Value,
 }
 
+impl ModelVersion {
+    /// Parse the inputs accepted by this version from its OpenAPI schema.
+    ///
+    /// Parsing is best-effort, any unexpected parts of the schema are skipped and reported in
+    /// `InputSchema::warnings`. An error is only returned if the version has no schema at all.
+    pub fn input_schema(&self) -> ReplicateResult<InputSchema> {
+        if !self.openapi_schema.is_object() {
+            return Err(ReplicateError::Misc(format!(
+                "no openapi schema available for version {}",
+                self.id
+            )));
+        }
+
+        Ok(parse_input_schema(&self.openapi_schema))
+    }
+
+    /// Extract the inputs accepted by this version as a standalone JSON Schema document, for
+    /// use with generic form renderers.
+    ///
+    /// This is the `components.schemas.Input` part of the OpenAPI schema, with references to
+    /// other components (such as enums) inlined. Recursive references are kept, pointing at
+    /// definitions under `$defs`.
+    pub fn input_json_schema(&self) -> ReplicateResult<Value> {
+        input_json_schema(&self.openapi_schema)
+    }
+}
+
 /// Paginated view of all versions for a particular model
 #[derive(Debug, Deserialize)]
 pub struct ModelVersions {
@@ -39,7 +69,8 @@ pub struct ModelVersions {
     pub next: Option<String>,
     /// Place in pagination
     pub previous: Option<String>,
-    /// List of all versions available
+    /// List of all versions available, which is empty for a model with no versions
+    #[serde(default)]
     pub results: Vec<ModelVersion>,
 }
 
@@ -50,10 +81,19 @@ pub struct Models {
     pub next: Option<String>,
     /// Place in pagination
     pub previous: Option<String>,
-    /// List of all versions available
+    /// List of all models available, which is empty for an account with no models
+    #[serde(default)]
     pub results: Vec<Model>,
 }
 
+impl Page for Models {
+    type Item = Model;
+
+    fn into_parts(self) -> (Vec<Model>, Option<String>) {
+        (self.results, self.next)
+    }
+}
+
 /// All details available for a particular Model
 #[derive(Deserialize, Debug)]
 pub struct Model {
@@ -79,36 +119,154 @@ pub struct Model {
     pub cover_image_url: String,
     /// A simple example to show model's use
     pub default_example: Value,
-    /// The latest version's details
-    pub latest_version: ModelVersion,
+    /// The latest version's details, or `None` for a model with no versions yet
+    pub latest_version: Option<ModelVersion>,
+}
+
+/// Who can see and run a model
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Visibility {
+    /// Anyone can see and run the model
+    Public,
+    /// Only the owner can see and run the model
+    Private,
 }
 
+/// Details of a model to create with `ModelClient::create`
+#[derive(Debug, Clone, Serialize)]
+pub struct CreateModelParams {
+    /// The user or organization which will own the model
+    pub owner: String,
+    /// The name of the model
+    pub name: String,
+    /// Whether the model is public or private
+    pub visibility: Visibility,
+    /// Hardware the model runs on
+    pub hardware: HardwareSku,
+    /// A brief description of the model
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Github URL for the associated repo
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub github_url: Option<String>,
+    /// Url for an associated paper
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub paper_url: Option<String>,
+    /// Url for the model's license
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub license_url: Option<String>,
+}
+
+/// The part of a model read by `get_latest_version`, which is `null` for a model with no
+/// versions
+#[derive(Deserialize)]
+struct LatestVersion {
+    latest_version: Option<ModelVersion>,
+}
+
+/// How long latest versions are cached by `get_latest_version_cached` by default
+const DEFAULT_VERSION_CACHE_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// Latest versions by `owner/name`, with the time they were fetched, shared by every clone of
+/// a `ReplicateConfig`
+pub(crate) type VersionCache = Arc<Mutex<HashMap<String, (Instant, ModelVersion)>>>;
+
 /// A client for interacting with `models` endpoints
+#[derive(Debug)]
 pub struct ModelClient {
-    client: ReplicateConfig,
+    config: ReplicateConfig,
+    version_cache_ttl: Duration,
 }
 
 impl ModelClient {
     /// Create a new `ModelClient` based upon a `ReplicateConfig` object
-    pub fn from(client: ReplicateConfig) -> Self {
-        ModelClient { client }
+    pub fn from(config: ReplicateConfig) -> Self {
+        ModelClient {
+            config,
+            version_cache_ttl: DEFAULT_VERSION_CACHE_TTL,
+        }
+    }
+
+    /// Set how long `get_latest_version_cached` reuses a model's latest version before looking
+    /// it up again. Defaults to five minutes.
+    pub fn with_version_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.version_cache_ttl = ttl;
+        self
+    }
+
+    /// Create a new model, with no versions until one is pushed to it
+    pub async fn create(&self, params: CreateModelParams) -> ReplicateResult<Model> {
+        let endpoint = self.config.endpoint("models");
+        let body = serde_json::to_string(&params).map_err(ReplicateError::from)?;
+        let data = self
+            .config
+            .send(self.config.request(Method::POST, endpoint)?.body(body))
+            .await?;
+
+        serde_json::from_str(&data).map_err(ReplicateError::from)
     }
 
     /// Retrieve details for a specific model
     pub async fn get(&self, owner: &str, name: &str) -> anyhow::Result<Model> {
-        let api_key = self.client.get_api_key()?;
-        let base_url = self.client.get_base_url();
-        let endpoint = format!("{base_url}/models/{owner}/{name}");
-        let client = reqwest::Client::new();
-        let response = client
-            .get(endpoint)
-            .header("Authorization", format!("Token {api_key}"))
-            .send()
+        anyhow::Ok(self.get_model(owner, name).await?)
+    }
+
+    /// Retrieve details for a specific model, along with the inputs accepted by its latest
+    /// version. Returns `ReplicateError::NotFound` if the model has no versions.
+    pub async fn get_with_schema(
+        &self,
+        owner: &str,
+        name: &str,
+    ) -> ReplicateResult<(Model, InputSchema)> {
+        let model = self.get_model(owner, name).await?;
+        let schema = model
+            .latest_version
+            .as_ref()
+            .ok_or_else(|| {
+                ReplicateError::NotFound(format!("no versions found for {owner}/{name}"))
+            })?
+            .input_schema()?;
+        Ok((model, schema))
+    }
+
+    async fn get_model(&self, owner: &str, name: &str) -> ReplicateResult<Model> {
+        let endpoint = self.config.endpoint(&format!("models/{owner}/{name}"));
+        let data = self
+            .config
+            .send(self.config.request(Method::GET, endpoint)?)
             .await?;
 
-        let data = response.text().await?;
-        let model: Model = serde_json::from_str(&data)?;
-        anyhow::Ok(model)
+        let model: Model = serde_json::from_str(&data).map_err(ReplicateError::from)?;
+        Ok(model)
+    }
+
+    /// Check whether a model can be accessed with the current api token.
+    ///
+    /// Returns `false` if Replicate responds that the model is forbidden or not found, such as a
+    /// private model owned by another account, and an error for any other failure.
+    pub async fn is_accessible(&self, owner: &str, name: &str) -> ReplicateResult<bool> {
+        let endpoint = self.config.endpoint(&format!("models/{owner}/{name}"));
+        let (client, request) = self.config.request(Method::GET, endpoint)?.build_split();
+        let request = request.map_err(ReplicateError::from)?;
+        let path = request.url().path().to_string();
+        let context = |err: ReplicateError| err.with_context(&Method::GET, &path);
+
+        let response = client
+            .execute(request)
+            .await
+            .map_err(|err| context(ReplicateError::from(err)))?;
+
+        let status = response.status();
+        match status {
+            status if status.is_success() => Ok(true),
+            reqwest::StatusCode::FORBIDDEN | reqwest::StatusCode::NOT_FOUND => Ok(false),
+            _ => {
+                let headers = response.headers().clone();
+                let data = self.config.read_body(response).await.map_err(context)?;
+                Err(context(get_error(status, &headers, data.as_str())))
+            }
+        }
     }
 
     /// Retrieve details for a specific model's version
@@ -118,23 +276,15 @@ impl ModelClient {
         name: &str,
         version_id: &str,
     ) -> ReplicateResult<Model> {
-        let api_key = self.client.get_api_key()?;
-        let base_url = self.client.get_base_url();
-        let endpoint = format!("{base_url}/models/{owner}/{name}/versions/{version_id}");
-        let client = reqwest::Client::new();
-        let response = client
-            .get(endpoint)
-            .header("Authorization", format!("Token {api_key}"))
-            .send()
-            .await
-            .map_err(|err| ReplicateError::ClientError(err.to_string()))?;
+        let endpoint = self
+            .config
+            .endpoint(&format!("models/{owner}/{name}/versions/{version_id}"));
+        let data = self
+            .config
+            .send(self.config.request(Method::GET, endpoint)?)
+            .await?;
 
-        let data = response
-            .text()
-            .await
-            .map_err(|err| ReplicateError::ClientError(err.to_string()))?;
-        let model: Model = serde_json::from_str(&data)
-            .map_err(|err| ReplicateError::SerializationError(err.to_string()))?;
+        let model: Model = serde_json::from_str(&data).map_err(ReplicateError::from)?;
         Ok(model)
     }
 
@@ -145,86 +295,189 @@ impl ModelClient {
         name: &str,
         version_id: &str,
     ) -> ReplicateResult<()> {
-        let api_key = self.client.get_api_key()?;
-        let base_url = self.client.get_base_url();
-        let endpoint = format!("{base_url}/models/{owner}/{name}/versions/{version_id}");
-        let client = reqwest::Client::new();
-        let response = client
-            .delete(endpoint)
-            .header("Authorization", format!("Token {api_key}"))
-            .send()
-            .await
-            .map_err(|err| ReplicateError::ClientError(err.to_string()))?;
-
-        if response.status().is_success() {
-            Ok(())
-        } else {
-            Err(ReplicateError::Misc("delete request failed".to_string()))
-        }
+        let endpoint = self
+            .config
+            .endpoint(&format!("models/{owner}/{name}/versions/{version_id}"));
+        self.config
+            .send(self.config.request(Method::DELETE, endpoint)?)
+            .await?;
+        Ok(())
     }
 
     /// Retrieve details for latest version of a specific model
+    ///
+    /// This reads the model's `latest_version` in a single request, rather than listing every
+    /// version with `list_versions`. Returns `ReplicateError::NotFound` if the model has no
+    /// versions, whereas `list_versions` returns an empty list.
     pub async fn get_latest_version(
         &self,
         owner: &str,
         name: &str,
     ) -> ReplicateResult<ModelVersion> {
-        let all_versions = self.list_versions(owner, name).await?;
-        let latest_version = all_versions.results.get(0).ok_or(ReplicateError::Misc(
-            "no versions found for {owner}/{name}".to_string(),
-        ))?;
-        Ok(latest_version.clone())
+        let endpoint = self.config.endpoint(&format!("models/{owner}/{name}"));
+        let data = self
+            .config
+            .send(self.config.request(Method::GET, endpoint)?)
+            .await?;
+
+        let model: LatestVersion = serde_json::from_str(&data).map_err(ReplicateError::from)?;
+        model.latest_version.ok_or_else(|| {
+            ReplicateError::NotFound(format!("no versions found for {owner}/{name}"))
+        })
+    }
+
+    /// Retrieve details for latest version of a specific model, reusing the result of a
+    /// previous lookup if it is younger than the cache ttl.
+    ///
+    /// This avoids a request per lookup for frequently used models, while still picking up new
+    /// versions once the cached one expires. The cache is shared by every client created from
+    /// the same `ReplicateConfig`, and by predictions created by model name once enabled with
+    /// `ReplicateConfig::with_version_lookup_cache`.
+    pub async fn get_latest_version_cached(
+        &self,
+        owner: &str,
+        name: &str,
+    ) -> ReplicateResult<ModelVersion> {
+        let slug = format!("{owner}/{name}");
+        let cached = self
+            .config
+            .version_cache()
+            .lock()
+            .unwrap()
+            .get(&slug)
+            .filter(|(fetched_at, _)| fetched_at.elapsed() < self.version_cache_ttl)
+            .map(|(_, version)| version.clone());
+        if let Some(version) = cached {
+            return Ok(version);
+        }
+
+        let version = self.get_latest_version(owner, name).await?;
+        self.config
+            .version_cache()
+            .lock()
+            .unwrap()
+            .insert(slug, (Instant::now(), version.clone()));
+        Ok(version)
+    }
+
+    /// Remove a model's cached latest version, so the next `get_latest_version_cached` looks
+    /// it up again
+    pub fn invalidate_latest_version(&self, owner: &str, name: &str) {
+        self.config
+            .version_cache()
+            .lock()
+            .unwrap()
+            .remove(&format!("{owner}/{name}"));
     }
 
     /// Retrieve list of all available versions of a specific model
     pub async fn list_versions(&self, owner: &str, name: &str) -> ReplicateResult<ModelVersions> {
-        let base_url = self.client.get_base_url();
-        let api_key = self.client.get_api_key()?;
-        let endpoint = format!("{base_url}/models/{owner}/{name}/versions");
-        let client = reqwest::Client::new();
-        let response = client
-            .get(endpoint)
-            .header("Authorization", format!("Token {api_key}"))
-            .send()
-            .await
-            .map_err(|err| ReplicateError::ClientError(err.to_string()))?;
+        let endpoint = self
+            .config
+            .endpoint(&format!("models/{owner}/{name}/versions"));
+        let data = self
+            .config
+            .send(self.config.request(Method::GET, endpoint)?)
+            .await?;
 
-        let status = response.status();
-        let data = response
-            .text()
+        let data: ModelVersions = serde_json::from_str(&data).map_err(ReplicateError::from)?;
+        Ok(data)
+    }
+
+    /// Retrieve the inputs of a model's example predictions, as chosen by its author. Each is a
+    /// known working input, suitable for pre-populating a form.
+    pub async fn example_inputs(&self, owner: &str, name: &str) -> ReplicateResult<Vec<Value>> {
+        let endpoint = self
+            .config
+            .endpoint(&format!("models/{owner}/{name}/examples"));
+        let data = self
+            .config
+            .send(self.config.request(Method::GET, endpoint)?)
+            .await?;
+
+        let data: Value = serde_json::from_str(&data).map_err(ReplicateError::from)?;
+        let examples = data.get("results").and_then(Value::as_array).ok_or(
+            ReplicateError::SerializationError(
+                "examples response has no results".to_string(),
+                None,
+            ),
+        )?;
+        Ok(examples
+            .iter()
+            .filter_map(|example| example.get("input").cloned())
+            .collect())
+    }
+
+    /// Retrieve the versions of several models concurrently, with at most `concurrency` requests
+    /// in flight. Each result is tagged with its `owner/name` slug, in the order requested.
+    pub async fn list_versions_many(
+        &self,
+        slugs: &[(String, String)],
+        concurrency: usize,
+    ) -> Vec<(String, ReplicateResult<ModelVersions>)> {
+        stream::iter(slugs)
+            .map(|(owner, name)| async move {
+                (
+                    format!("{owner}/{name}"),
+                    self.list_versions(owner, name).await,
+                )
+            })
+            .buffered(concurrency.max(1))
+            .collect()
             .await
-            .map_err(|err| ReplicateError::ClientError(err.to_string()))?;
+    }
 
-        return match status.clone() {
-            reqwest::StatusCode::OK => {
-                let data: ModelVersions = serde_json::from_str(&data)
-                    .map_err(|err| ReplicateError::SerializationError(err.to_string()))?;
-                Ok(data)
-            }
-            _ => Err(get_error(status, data.as_str())),
-        };
+    /// Search public models by a free text query, such as `stable diffusion`
+    ///
+    /// Replicate's search uses the `QUERY` http method with the query as a plain text body,
+    /// so it is sent as is rather than url encoded.
+    pub async fn search(&self, query: &str) -> ReplicateResult<Models> {
+        let endpoint = self.config.endpoint("models");
+        let method = Method::from_bytes(b"QUERY")
+            .map_err(|err| ReplicateError::InvalidRequest(err.to_string()))?;
+        let data = self
+            .config
+            .send(
+                self.config
+                    .request(method, endpoint)?
+                    .header(reqwest::header::CONTENT_TYPE, "text/plain")
+                    .body(query.to_string()),
+            )
+            .await?;
+
+        let models: Models = serde_json::from_str(&data).map_err(ReplicateError::from)?;
+        Ok(models)
     }
 
     /// Retrieve all publically and private available models
     pub async fn get_models(&self) -> ReplicateResult<Models> {
-        let base_url = self.client.get_base_url();
-        let api_key = self.client.get_api_key()?;
-        let endpoint = format!("{base_url}/models");
-        let client = reqwest::Client::new();
-        let response = client
-            .get(endpoint)
-            .header("Authorization", format!("Token {api_key}"))
-            .send()
-            .await
-            .map_err(|err| ReplicateError::ClientError(err.to_string()))?;
+        self.get_models_page(None).await
+    }
 
-        let data = response
-            .text()
-            .await
-            .map_err(|err| ReplicateError::ClientError(err.to_string()))?;
-        let models: Models = serde_json::from_str(&data)
-            .map_err(|err| ReplicateError::SerializationError(err.to_string()))?;
-        Ok(models)
+    /// Fetch a single page of public models. `None` fetches the first page, and otherwise
+    /// `cursor` may be either a `next` or `previous` url from an earlier page, or the bare
+    /// `cursor` value from one.
+    pub async fn get_models_page(&self, cursor: Option<&str>) -> ReplicateResult<Models> {
+        let endpoint = page_url(&self.config, "models", cursor)?;
+        let data = self
+            .config
+            .send(self.config.request(Method::GET, endpoint)?)
+            .await?;
+
+        serde_json::from_str(&data).map_err(ReplicateError::from)
+    }
+
+    /// List all public models, following the `next` cursor of each page until there are no
+    /// more.
+    ///
+    /// There are a great many public models, so `max_pages` can be set to stop after that
+    /// many pages.
+    pub async fn list_all_models(&self, max_pages: Option<usize>) -> ReplicateResult<Vec<Model>> {
+        let endpoint = self.config.endpoint("models");
+        collect_pages(endpoint, max_pages, |url| async move {
+            self.get_models_page(Some(&url)).await
+        })
+        .await
     }
 }
 
@@ -268,6 +521,113 @@ mod tests {
         model_mock.assert();
     }
 
+    #[tokio::test]
+    async fn test_is_accessible() {
+        let mock_server = MockServer::start();
+
+        mock_server.mock(|when, then| {
+            when.method(GET).path("/models/replicate/hello-world");
+            then.status(200).json_body_obj(&json!({}));
+        });
+        mock_server.mock(|when, then| {
+            when.method(GET).path("/models/someone/private");
+            then.status(403).json_body_obj(&json!({
+                "title": "Forbidden",
+                "detail": "You do not have permission to access this model"
+            }));
+        });
+        mock_server.mock(|when, then| {
+            when.method(GET).path("/models/replicate/missing");
+            then.status(404).json_body_obj(&json!({
+                "title": "Not found",
+                "detail": "Not found."
+            }));
+        });
+        mock_server.mock(|when, then| {
+            when.method(GET).path("/models/replicate/broken");
+            then.status(500).json_body_obj(&json!({
+                "title": "Internal server error",
+                "detail": "Something went wrong"
+            }));
+        });
+
+        let client = ReplicateConfig::test(mock_server.base_url()).unwrap();
+        let model_client = ModelClient::from(client);
+
+        assert!(model_client
+            .is_accessible("replicate", "hello-world")
+            .await
+            .unwrap());
+        assert!(!model_client
+            .is_accessible("someone", "private")
+            .await
+            .unwrap());
+        assert!(!model_client
+            .is_accessible("replicate", "missing")
+            .await
+            .unwrap());
+        assert!(model_client
+            .is_accessible("replicate", "broken")
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_get_with_schema() {
+        let mock_server = MockServer::start();
+
+        let model_mock = mock_server.mock(|when, then| {
+            when.method(GET).path("/models/replicate/hello-world");
+            then.status(200).json_body_obj(&json!({
+                "url": "https://replicate.com/replicate/hello-world",
+                "owner": "replicate",
+                "name": "hello-world",
+                "description": "A tiny model that says hello",
+                "visibility": "public",
+                "github_url": "https://github.com/replicate/cog-examples",
+                "paper_url": null,
+                "license_url": null,
+                "run_count": 5681081,
+                "cover_image_url": "...",
+                "default_example": null,
+                "latest_version": {
+                    "id": "5c7d5dc6dd8bf75c1acaa8565735e7986bc5b66206b55cca93cb72c9bf15ccaa",
+                    "created_at": "2022-04-26T19:29:04.418669Z",
+                    "cog_version": "0.3.0",
+                    "openapi_schema": {
+                        "components": {
+                            "schemas": {
+                                "Input": {
+                                    "type": "object",
+                                    "required": ["text"],
+                                    "properties": {
+                                        "text": {
+                                            "type": "string",
+                                            "description": "Text to prefix with 'hello '"
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }));
+        });
+
+        let client = ReplicateConfig::test(mock_server.base_url()).unwrap();
+        let model_client = ModelClient::from(client);
+        let (model, schema) = model_client
+            .get_with_schema("replicate", "hello-world")
+            .await
+            .unwrap();
+
+        assert_eq!(model.name, "hello-world");
+        assert_eq!(schema.properties.len(), 1);
+        assert_eq!(schema.properties[0].name, "text");
+        assert!(schema.properties[0].required);
+        model_mock.assert();
+    }
+
     #[tokio::test]
     async fn test_get_specific_version() {
         let mock_server = MockServer::start();
@@ -337,23 +697,52 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_get_latest_version() {
+    async fn test_list_model_versions_empty() {
         let mock_server = MockServer::start();
 
-        // Model endpoints
-        let model_mock = mock_server.mock(|when, then| {
+        mock_server.mock(|when, then| {
             when.method(GET)
                 .path("/models/replicate/hello-world/versions");
 
             then.status(200).json_body_obj(&json!({
                 "next": null,
                 "previous": null,
-                "results": [{
+                "results": []
+            }));
+        });
+        mock_server.mock(|when, then| {
+            when.method(GET)
+                .path("/models/replicate/no-results/versions");
+
+            then.status(200).json_body_obj(&json!({
+                "next": null,
+                "previous": null
+            }));
+        });
+
+        let client = ReplicateConfig::test(mock_server.base_url()).unwrap();
+        let model_client = ModelClient::from(client);
+        for name in ["hello-world", "no-results"] {
+            let versions = model_client.list_versions("replicate", name).await.unwrap();
+            assert!(versions.results.is_empty());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_latest_version() {
+        let mock_server = MockServer::start();
+
+        // Model endpoints
+        let model_mock = mock_server.mock(|when, then| {
+            when.method(GET).path("/models/replicate/hello-world");
+
+            then.status(200).json_body_obj(&json!({
+                "latest_version": {
                     "id": "5c7d5dc6dd8bf75c1acaa8565735e7986bc5b66206b55cca93cb72c9bf15ccaa",
                     "created_at": "2022-04-26T19:29:04.418669Z",
                     "cog_version": "0.3.0",
                     "openapi_schema": null
-                }]
+                }
             }));
         });
 
@@ -367,6 +756,115 @@ mod tests {
         model_mock.assert();
     }
 
+    #[tokio::test]
+    async fn test_get_latest_version_cached() {
+        let mock_server = MockServer::start();
+
+        let model_mock = mock_server.mock(|when, then| {
+            when.method(GET).path("/models/replicate/hello-world");
+
+            then.status(200).json_body_obj(&json!({
+                "latest_version": {
+                    "id": "5c7d5dc6dd8bf75c1acaa8565735e7986bc5b66206b55cca93cb72c9bf15ccaa",
+                    "created_at": "2022-04-26T19:29:04.418669Z",
+                    "cog_version": "0.3.0",
+                    "openapi_schema": null
+                }
+            }));
+        });
+
+        let client = ReplicateConfig::test(mock_server.base_url()).unwrap();
+        let model_client = ModelClient::from(client);
+        for _ in 0..2 {
+            model_client
+                .get_latest_version_cached("replicate", "hello-world")
+                .await
+                .unwrap();
+        }
+        model_mock.assert_hits(1);
+
+        model_client.invalidate_latest_version("replicate", "hello-world");
+        model_client
+            .get_latest_version_cached("replicate", "hello-world")
+            .await
+            .unwrap();
+        model_mock.assert_hits(2);
+
+        let model_client = model_client.with_version_cache_ttl(Duration::ZERO);
+        model_client
+            .get_latest_version_cached("replicate", "hello-world")
+            .await
+            .unwrap();
+        model_mock.assert_hits(3);
+    }
+
+    #[tokio::test]
+    async fn test_get_latest_version_none_found() {
+        let mock_server = MockServer::start();
+
+        mock_server.mock(|when, then| {
+            when.method(GET).path("/models/replicate/hello-world");
+
+            then.status(200).json_body_obj(&json!({
+                "latest_version": null
+            }));
+        });
+
+        let client = ReplicateConfig::test(mock_server.base_url()).unwrap();
+        let model_client = ModelClient::from(client);
+        let latest_version = model_client
+            .get_latest_version("replicate", "hello-world")
+            .await;
+
+        match latest_version {
+            Err(ReplicateError::NotFound(message)) => {
+                assert_eq!(message, "no versions found for replicate/hello-world")
+            }
+            other => panic!("expected NotFound, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_example_inputs() {
+        let mock_server = MockServer::start();
+
+        let examples_mock = mock_server.mock(|when, then| {
+            when.method(GET)
+                .path("/models/replicate/hello-world/examples");
+            then.status(200).json_body_obj(&json!({
+                "next": null,
+                "previous": null,
+                "results": [
+                    {
+                        "id": "1234",
+                        "input": {"text": "Alice"},
+                        "output": "hello Alice",
+                        "status": "succeeded"
+                    },
+                    {
+                        "id": "5678",
+                        "input": {"text": "Bob"},
+                        "output": "hello Bob",
+                        "status": "succeeded"
+                    }
+                ]
+            }));
+        });
+
+        let client = ReplicateConfig::test(mock_server.base_url()).unwrap();
+        let model_client = ModelClient::from(client);
+        let inputs = model_client
+            .example_inputs("replicate", "hello-world")
+            .await
+            .unwrap();
+
+        assert_eq!(
+            inputs,
+            vec![json!({"text": "Alice"}), json!({"text": "Bob"})]
+        );
+        examples_mock.assert();
+    }
+
     #[tokio::test]
     async fn test_get_models() {
         let mock_server = MockServer::start();
@@ -429,4 +927,317 @@ mod tests {
 
         model_mock.assert();
     }
+
+    #[tokio::test]
+    async fn test_list_all_models_and_get_models_page() {
+        let mock_server = MockServer::start();
+
+        let model = |name: &str| {
+            json!({
+                "url": format!("https://replicate.com/jdoe/{name}"),
+                "owner": "jdoe",
+                "name": name,
+                "description": "",
+                "visibility": "public",
+                "github_url": "",
+                "paper_url": null,
+                "license_url": null,
+                "run_count": 0,
+                "cover_image_url": "",
+                "default_example": null,
+                "latest_version": null
+            })
+        };
+
+        let second_page = mock_server.mock(|when, then| {
+            when.method(GET)
+                .path("/models")
+                .query_param("cursor", "abc");
+            then.status(200).json_body_obj(&json!({
+                "next": null,
+                "previous": format!("{}/models", mock_server.base_url()),
+                "results": [model("third")]
+            }));
+        });
+
+        let first_page = mock_server.mock(|when, then| {
+            when.method(GET).path("/models");
+            then.status(200).json_body_obj(&json!({
+                "next": format!("{}/models?cursor=abc", mock_server.base_url()),
+                "previous": null,
+                "results": [model("first"), model("second")]
+            }));
+        });
+
+        let client = ReplicateConfig::test(mock_server.base_url()).unwrap();
+        let model_client = ModelClient::from(client);
+
+        let names = |models: &[Model]| -> Vec<String> {
+            models.iter().map(|model| model.name.clone()).collect()
+        };
+
+        let models = model_client.list_all_models(None).await.unwrap();
+        assert_eq!(names(&models), vec!["first", "second", "third"]);
+        first_page.assert_hits(1);
+        second_page.assert_hits(1);
+
+        let models = model_client.list_all_models(Some(1)).await.unwrap();
+        assert_eq!(names(&models), vec!["first", "second"]);
+        first_page.assert_hits(2);
+        second_page.assert_hits(1);
+
+        let page = model_client.get_models_page(Some("abc")).await.unwrap();
+        assert_eq!(names(&page.results), vec!["third"]);
+        second_page.assert_hits(2);
+
+        let err = model_client
+            .get_models_page(Some("https://example.com/models?cursor=abc"))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ReplicateError::InvalidRequest(_)));
+    }
+
+    #[tokio::test]
+    async fn test_search() {
+        let mock_server = MockServer::start();
+
+        let search_mock = mock_server.mock(|when, then| {
+            // httpmock can't match a QUERY method directly
+            when.matches(|request| request.method == "QUERY")
+                .path("/models")
+                .header("Content-Type", "text/plain")
+                .body("stable diffusion & upscale?");
+            then.status(200).json_body_obj(&json!({
+                "next": null,
+                "previous": null,
+                "results": [{
+                    "url": "https://replicate.com/stability-ai/stable-diffusion",
+                    "owner": "stability-ai",
+                    "name": "stable-diffusion",
+                    "description": "A latent text-to-image diffusion model",
+                    "visibility": "public",
+                    "github_url": "https://github.com/replicate/cog-stable-diffusion",
+                    "paper_url": null,
+                    "license_url": null,
+                    "run_count": 420,
+                    "cover_image_url": "https://replicate.delivery/pbxt/cover.png",
+                    "default_example": null,
+                    "latest_version": {
+                        "id": "ac732df83cea7fff18b8472768c88ad041fa750ff7682a21affe81863cbe77e4",
+                        "created_at": "2022-11-21T20:08:39.011484Z",
+                        "cog_version": "0.6.1",
+                        "openapi_schema": null
+                    }
+                }]
+            }));
+        });
+
+        let client = ReplicateConfig::test(mock_server.base_url()).unwrap();
+        let model_client = ModelClient::from(client);
+        let models = model_client
+            .search("stable diffusion & upscale?")
+            .await
+            .unwrap();
+
+        assert_eq!(models.results.len(), 1);
+        assert_eq!(models.results[0].name, "stable-diffusion");
+        search_mock.assert();
+    }
+
+    #[tokio::test]
+    async fn test_create() {
+        let mock_server = MockServer::start();
+
+        let create_mock = mock_server.mock(|when, then| {
+            when.method(POST).path("/models").json_body(json!({
+                "owner": "acme",
+                "name": "hot-dog-detector",
+                "visibility": "private",
+                "hardware": "gpu-t4",
+                "description": "Detect hot dogs in images"
+            }));
+            then.status(201).json_body_obj(&json!({
+                "url": "https://replicate.com/acme/hot-dog-detector",
+                "owner": "acme",
+                "name": "hot-dog-detector",
+                "description": "Detect hot dogs in images",
+                "visibility": "private",
+                "github_url": "",
+                "paper_url": null,
+                "license_url": null,
+                "run_count": 0,
+                "cover_image_url": "",
+                "default_example": null,
+                "latest_version": null
+            }));
+        });
+
+        let client = ReplicateConfig::test(mock_server.base_url()).unwrap();
+        let model_client = ModelClient::from(client);
+        let model = model_client
+            .create(CreateModelParams {
+                owner: "acme".to_string(),
+                name: "hot-dog-detector".to_string(),
+                visibility: Visibility::Private,
+                hardware: HardwareSku::GpuT4,
+                description: Some("Detect hot dogs in images".to_string()),
+                github_url: None,
+                paper_url: None,
+                license_url: None,
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(model.name, "hot-dog-detector");
+        assert!(model.latest_version.is_none());
+        create_mock.assert();
+    }
+
+    #[tokio::test]
+    async fn test_get_models_without_versions() {
+        let mock_server = MockServer::start();
+
+        let model = |name: &str, latest_version: Value| {
+            json!({
+                "url": format!("https://replicate.com/acme/{name}"),
+                "owner": "acme",
+                "name": name,
+                "description": "An internal model",
+                "visibility": "private",
+                "github_url": "https://github.com/acme/models",
+                "paper_url": null,
+                "license_url": null,
+                "run_count": 0,
+                "cover_image_url": "https://cdn.example.com/cover.jpg",
+                "default_example": null,
+                "latest_version": latest_version
+            })
+        };
+        mock_server.mock(|when, then| {
+            when.method(GET).path("/models");
+            then.status(200).json_body_obj(&json!({
+                "next": null,
+                "previous": null,
+                "results": [
+                    model("pushed", json!({
+                        "id": "5c7d5dc6dd8bf75c1acaa8565735e7986bc5b66206b55cca93cb72c9bf15ccaa",
+                        "created_at": "2022-04-26T19:29:04.418669Z",
+                        "cog_version": "0.3.0",
+                        "openapi_schema": null
+                    })),
+                    model("unpushed", Value::Null)
+                ]
+            }));
+        });
+
+        let client = ReplicateConfig::test(mock_server.base_url()).unwrap();
+        let model_client = ModelClient::from(client);
+        let models = model_client.get_models().await.unwrap();
+
+        assert_eq!(models.results.len(), 2);
+        assert!(models.results[0].latest_version.is_some());
+        assert!(models.results[1].latest_version.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_get_models_empty() {
+        let mock_server = MockServer::start();
+
+        let model_mock = mock_server.mock(|when, then| {
+            when.method(GET).path("/models");
+            then.status(200).json_body_obj(&json!({
+                "next": null,
+                "previous": null,
+                "results": []
+            }));
+        });
+
+        let client = ReplicateConfig::test(mock_server.base_url()).unwrap();
+        let model_client = ModelClient::from(client);
+        let models = model_client.get_models().await.unwrap();
+        assert!(models.results.is_empty());
+        assert!(models.next.is_none());
+
+        model_mock.assert();
+    }
+
+    #[tokio::test]
+    async fn test_max_response_size() {
+        let mock_server = MockServer::start();
+
+        mock_server.mock(|when, then| {
+            when.method(GET)
+                .path("/models/replicate/hello-world/versions");
+
+            then.status(200).json_body_obj(&json!({
+                "next": null,
+                "previous": null,
+                "results": [{
+                    "id": "5c7d5dc6dd8bf75c1acaa8565735e7986bc5b66206b55cca93cb72c9bf15ccaa",
+                    "created_at": "2022-04-26T19:29:04.418669Z",
+                    "cog_version": "0.3.0",
+                    "openapi_schema": null
+                }]
+            }));
+        });
+
+        let config = ReplicateConfig::test(mock_server.base_url())
+            .unwrap()
+            .with_max_response_size(64);
+        let model_client = ModelClient::from(config);
+        let err = model_client
+            .list_versions("replicate", "hello-world")
+            .await
+            .unwrap_err();
+
+        assert!(matches!(err.inner(), ReplicateError::PayloadTooLarge(_)));
+        assert_eq!(
+            err.context(),
+            Some(("GET", "/models/replicate/hello-world/versions"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_list_versions_many() {
+        let mock_server = MockServer::start();
+
+        let hello_mock = mock_server.mock(|when, then| {
+            when.method(GET)
+                .path("/models/replicate/hello-world/versions");
+
+            then.status(200).json_body_obj(&json!({
+                "next": null,
+                "previous": null,
+                "results": [{
+                    "id": "5c7d5dc6dd8bf75c1acaa8565735e7986bc5b66206b55cca93cb72c9bf15ccaa",
+                    "created_at": "2022-04-26T19:29:04.418669Z",
+                    "cog_version": "0.3.0",
+                    "openapi_schema": null
+                }]
+            }));
+        });
+        let missing_mock = mock_server.mock(|when, then| {
+            when.method(GET).path("/models/replicate/missing/versions");
+            then.status(404).json_body_obj(&json!({
+                "title": "Not found",
+                "detail": "Not found."
+            }));
+        });
+
+        let config = ReplicateConfig::test(mock_server.base_url()).unwrap();
+        let model_client = ModelClient::from(config);
+        let slugs = vec![
+            ("replicate".to_string(), "hello-world".to_string()),
+            ("replicate".to_string(), "missing".to_string()),
+        ];
+        let results = model_client.list_versions_many(&slugs, 2).await;
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].0, "replicate/hello-world");
+        assert_eq!(results[0].1.as_ref().unwrap().results.len(), 1);
+        assert_eq!(results[1].0, "replicate/missing");
+        assert!(results[1].1.is_err());
+        hello_mock.assert();
+        missing_mock.assert();
+    }
 }
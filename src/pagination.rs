@@ -0,0 +1,90 @@
+//! Helpers shared by the paginated list endpoints.
+//!
+//! Replicate pages its list endpoints with `next` and `previous` cursor urls, which these
+//! helpers resolve and follow.
+use std::future::Future;
+
+use crate::config::ReplicateConfig;
+use crate::errors::{ReplicateError, ReplicateResult};
+
+/// A single page of results from a paginated list endpoint
+pub(crate) trait Page {
+    /// The type of each result on the page
+    type Item;
+
+    /// Split the page into its results and the url of the next page, if any
+    fn into_parts(self) -> (Vec<Self::Item>, Option<String>);
+}
+
+/// Check that `url` points at the list endpoint for `path`, so that cursor urls cannot send
+/// the api key anywhere else.
+pub(crate) fn check_page_url(
+    config: &ReplicateConfig,
+    path: &str,
+    url: &str,
+) -> ReplicateResult<()> {
+    let list_url = config.endpoint(path);
+    let is_list_url = url
+        .strip_prefix(&list_url)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('?'));
+    if !is_list_url {
+        return Err(ReplicateError::InvalidRequest(format!(
+            "{url} is not a {path} url under {list_url}"
+        )));
+    }
+
+    Ok(())
+}
+
+/// Resolve a cursor into the url of a page of the list endpoint for `path`.
+///
+/// `None` resolves to the first page, and otherwise `cursor` may be either a `next` or
+/// `previous` url from an earlier page, or the bare `cursor` value from one.
+pub(crate) fn page_url(
+    config: &ReplicateConfig,
+    path: &str,
+    cursor: Option<&str>,
+) -> ReplicateResult<String> {
+    match cursor {
+        None => Ok(config.endpoint(path)),
+        Some(url) if url.starts_with("https://") || url.starts_with("http://") => {
+            check_page_url(config, path, url)?;
+            Ok(url.to_string())
+        }
+        Some(cursor) => {
+            reqwest::Url::parse_with_params(&config.endpoint(path), [("cursor", cursor)])
+                .map(String::from)
+                .map_err(|err| ReplicateError::InvalidRequest(err.to_string()))
+        }
+    }
+}
+
+/// Fetch every page starting from `url`, following the `next` cursor of each until there
+/// are no more, and collect their results.
+///
+/// If `max_pages` is set, at most that many pages are fetched.
+pub(crate) async fn collect_pages<P, F, Fut>(
+    url: String,
+    max_pages: Option<usize>,
+    mut fetch: F,
+) -> ReplicateResult<Vec<P::Item>>
+where
+    P: Page,
+    F: FnMut(String) -> Fut,
+    Fut: Future<Output = ReplicateResult<P>>,
+{
+    let mut results = Vec::new();
+    let mut next = Some(url);
+    let mut pages = 0;
+    while let Some(url) = next {
+        if max_pages.is_some_and(|max_pages| pages >= max_pages) {
+            break;
+        }
+        let (page, page_next) = fetch(url).await?.into_parts();
+        pages += 1;
+        results.extend(page);
+        next = page_next;
+    }
+
+    Ok(results)
+}
@@ -7,20 +7,32 @@
 //! - [Cancel Prediction](https://replicate.com/docs/reference/http#predictions.cancel)
 //!
 
-use crate::config::ReplicateConfig;
-use crate::errors::{get_error, ReplicateError, ReplicateResult};
+use crate::config::{send, ReplicateConfig};
+use crate::errors::{ReplicateError, ReplicateResult};
 
-use anyhow::anyhow;
-use bytes::Bytes;
-use eventsource_stream::{EventStream, Eventsource};
-use futures_lite::StreamExt;
-use serde_json::Value;
+use chrono::DateTime;
+use eventsource_stream::{Event, EventStreamError, Eventsource};
+use futures::future::{self, try_join_all};
+use futures::stream::{self, FuturesUnordered, StreamExt};
+use futures_lite::Stream;
+use futures_timer::Delay;
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+use std::fmt;
+use std::future::Future;
+#[cfg(feature = "tokio")]
+use std::ops::{Deref, DerefMut};
+use std::pin::Pin;
+use std::sync::mpsc::Sender;
+use std::task::{Context, Poll};
+use std::time::{Duration, SystemTime};
 
-use crate::models::ModelClient;
-use crate::{api_key, base_url};
+use crate::models::{Model, ModelClient, ModelVersion};
+use crate::pagination::{check_page_url, collect_pages, page_url, Page};
+use reqwest::{Method, RequestBuilder};
 
 /// Status of a retrieved or created prediction
-#[derive(serde::Serialize, serde::Deserialize, Debug, Eq, PartialEq, Clone)]
+#[derive(serde::Serialize, serde::Deserialize, Debug, Eq, PartialEq, Hash, Clone)]
 #[serde(rename_all = "lowercase")]
 pub enum PredictionStatus {
     /// The prediction is starting up. If this status lasts longer than a few seconds, then it's
@@ -30,10 +42,47 @@ pub enum PredictionStatus {
     Processing,
     /// The prediction completed successfully.
     Succeeded,
-    /// The prediction was canceled by its creator.
+    /// The prediction encountered an error during processing. The reason is available from
+    /// `Prediction::failure_reason`.
     Failed,
     /// The prediction was canceled by its creator.
     Canceled,
+    /// A status not yet known to this crate. Treated as non-terminal.
+    #[serde(other)]
+    Unknown,
+}
+
+impl PredictionStatus {
+    /// Whether the prediction has finished, successfully or not, and will not change again.
+    /// `Unknown` statuses are not terminal, so wait loops keep polling through them.
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            self,
+            PredictionStatus::Succeeded | PredictionStatus::Failed | PredictionStatus::Canceled
+        )
+    }
+
+    /// Whether the prediction is starting up or currently running
+    pub fn is_running(&self) -> bool {
+        matches!(
+            self,
+            PredictionStatus::Starting | PredictionStatus::Processing
+        )
+    }
+}
+
+/// Prediction events which trigger a webhook request, see `PredictionClient::create_with_webhook`
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum WebhookEvent {
+    /// The prediction has started
+    Start,
+    /// The prediction has produced new output
+    Output,
+    /// The prediction has produced new logs
+    Logs,
+    /// The prediction has reached a terminal status
+    Completed,
 }
 
 /// Provided urls to either cancel or retrieve updated details for the specific prediction.
@@ -47,6 +96,17 @@ pub struct PredictionUrls {
     pub stream: Option<String>,
 }
 
+/// Metrics recorded by Replicate for a completed prediction
+#[derive(serde::Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct PredictionMetrics {
+    /// Seconds spent running the model, which is what the prediction is billed on
+    #[serde(default)]
+    pub predict_time: Option<f64>,
+}
+
+/// Scheme of the strings produced by `Prediction::to_permalink`
+const PERMALINK_PREFIX: &str = "replicate-prediction";
+
 /// Details for a specific prediction
 #[derive(serde::Deserialize, Debug)]
 pub struct Prediction {
@@ -62,10 +122,198 @@ pub struct Prediction {
     pub status: PredictionStatus,
     /// The created time for the prediction
     pub created_at: String,
+    /// The time the model started running the prediction
+    #[serde(default)]
+    pub started_at: Option<String>,
+    /// The time the prediction finished running
+    #[serde(default)]
+    pub completed_at: Option<String>,
     /// Urls to either retrieve or cancel details for this prediction
     pub urls: PredictionUrls,
     /// The output of the prediction if completed
     pub output: Option<Value>,
+    /// Logs written by the model so far, such as progress updates
+    #[serde(default)]
+    pub logs: Option<String>,
+    /// Why the prediction failed, if it did. This is usually a message, but some models
+    /// return a structured error.
+    #[serde(default)]
+    pub error: Option<Value>,
+    /// Metrics for the prediction, available once it has completed
+    #[serde(default)]
+    pub metrics: Option<PredictionMetrics>,
+    /// Number of array output items already returned by `new_output_items`
+    #[serde(skip)]
+    seen_output_items: usize,
+    /// Config of the client which retrieved this prediction
+    #[serde(skip)]
+    pub(crate) config: Option<ReplicateConfig>,
+    /// Time by which the prediction must have started, or be canceled
+    #[serde(skip)]
+    start_deadline: Option<SystemTime>,
+}
+
+/// Polling schedule used while waiting on a prediction.
+///
+/// While a prediction is `Starting` a cold boot may take minutes, so the first poll is made
+/// after `initial_interval`. Once `Processing`, output is usually closer, so polling restarts
+/// from the shorter `processing_interval`. In either status each interval grows by
+/// `backoff_multiplier` up to `max_interval`, so long running predictions are not polled as
+/// aggressively as short ones.
+#[derive(Debug, Clone)]
+pub struct PredictionPollConfig {
+    /// Interval before the first poll while the prediction is starting
+    pub initial_interval: Duration,
+    /// Upper bound on the interval between polls
+    pub max_interval: Duration,
+    /// Factor the interval grows by after each poll
+    pub backoff_multiplier: f64,
+    /// Interval before the first poll once the model is processing
+    pub processing_interval: Duration,
+}
+
+impl Default for PredictionPollConfig {
+    fn default() -> Self {
+        PredictionPollConfig {
+            initial_interval: Duration::from_millis(500),
+            max_interval: Duration::from_secs(10),
+            backoff_multiplier: 1.5,
+            processing_interval: Duration::from_millis(500),
+        }
+    }
+}
+
+impl PredictionPollConfig {
+    /// Interval to wait before the next poll, given the current status and how many polls
+    /// have already been made in that status
+    pub fn interval(&self, status: &PredictionStatus, polls: u32) -> Duration {
+        let first = match status {
+            PredictionStatus::Processing => self.processing_interval,
+            _ => self.initial_interval,
+        };
+        let factor = self
+            .backoff_multiplier
+            .powi(polls.min(i32::MAX as u32) as i32);
+        Duration::try_from_secs_f64(first.as_secs_f64() * factor)
+            .unwrap_or(self.max_interval)
+            .min(self.max_interval)
+    }
+}
+
+/// How many polls have been made while a prediction was starting and while it was processing,
+/// so that each status backs off from its own first interval
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct PollBackoff {
+    starting_polls: u32,
+    processing_polls: u32,
+}
+
+impl PollBackoff {
+    /// Interval to wait before the next poll of a prediction with `status`, counting the poll
+    pub(crate) fn next_interval(
+        &mut self,
+        config: &PredictionPollConfig,
+        status: &PredictionStatus,
+    ) -> Duration {
+        let polls = match status {
+            PredictionStatus::Processing => &mut self.processing_polls,
+            _ => &mut self.starting_polls,
+        };
+        let interval = config.interval(status, *polls);
+        *polls = polls.saturating_add(1);
+        interval
+    }
+}
+
+/// Durations between the recorded stages of a prediction's lifecycle
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Timeline {
+    /// Time spent queued between creation and the model starting, if it has started
+    pub queue_time: Option<Duration>,
+    /// Time spent running the model, if it has completed
+    pub run_time: Option<Duration>,
+}
+
+/// A single difference between two JSON values
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValueChange {
+    /// JSON pointer to the differing value, empty for the value as a whole
+    pub path: String,
+    /// The value in the first prediction, if present
+    pub left: Option<Value>,
+    /// The value in the second prediction, if present
+    pub right: Option<Value>,
+}
+
+/// Differences between two predictions, as reported by `Prediction::diff`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PredictionDiff {
+    /// Both version ids, if they differ
+    pub version: Option<(String, String)>,
+    /// Both statuses, if they differ
+    pub status: Option<(PredictionStatus, PredictionStatus)>,
+    /// Differences within the inputs
+    pub input: Vec<ValueChange>,
+    /// Differences within the outputs
+    pub output: Vec<ValueChange>,
+}
+
+impl PredictionDiff {
+    /// Whether the predictions matched on every compared field
+    pub fn is_empty(&self) -> bool {
+        self.version.is_none()
+            && self.status.is_none()
+            && self.input.is_empty()
+            && self.output.is_empty()
+    }
+}
+
+/// Options for collecting a prediction's streamed output with `Prediction::collect_stream_with`
+#[derive(Debug, Clone, Default)]
+pub struct CollectStreamOptions {
+    /// Remove trailing whitespace, including newlines, from the collected output. Off by
+    /// default, so output is returned verbatim.
+    pub trim_trailing_whitespace: bool,
+}
+
+/// Filters applied when listing predictions with `PredictionClient::list_filtered`.
+///
+/// `created_after` and `created_before` are sent to Replicate as query parameters and
+/// filtered server side. `status` and `model` are not supported by the API, and are emulated
+/// by filtering each page client side.
+#[derive(Debug, Clone, Default)]
+pub struct PredictionFilter {
+    /// Only include predictions created after this ISO 8601 timestamp (server side)
+    pub created_after: Option<String>,
+    /// Only include predictions created before this ISO 8601 timestamp (server side)
+    pub created_before: Option<String>,
+    /// Only include predictions with this status (client side)
+    pub status: Option<PredictionStatus>,
+    /// Only include predictions for this `owner/name` model (client side)
+    pub model: Option<String>,
+}
+
+impl PredictionFilter {
+    fn query(&self) -> Vec<(&'static str, &str)> {
+        let mut query = Vec::new();
+        if let Some(created_after) = &self.created_after {
+            query.push(("created_after", created_after.as_str()));
+        }
+        if let Some(created_before) = &self.created_before {
+            query.push(("created_before", created_before.as_str()));
+        }
+        query
+    }
+
+    fn matches(&self, prediction: &Prediction) -> bool {
+        self.status
+            .as_ref()
+            .is_none_or(|status| &prediction.status == status)
+            && self
+                .model
+                .as_ref()
+                .is_none_or(|model| &prediction.model == model)
+    }
 }
 
 /// Paginated list of available predictions
@@ -75,421 +323,3426 @@ pub struct Predictions {
     pub next: Option<String>,
     /// Identify for status of pagination
     pub previous: Option<String>,
-    /// List of predictions
+    /// List of predictions, which is empty if none have been created
+    #[serde(default)]
     pub results: Vec<Prediction>,
 }
 
+impl Page for Predictions {
+    type Item = Prediction;
+
+    fn into_parts(self) -> (Vec<Prediction>, Option<String>) {
+        (self.results, self.next)
+    }
+}
+
+/// The number of predictions on a page, so pages can be counted without keeping their results
+struct PageCount(Predictions);
+
+impl Page for PageCount {
+    type Item = usize;
+
+    fn into_parts(self) -> (Vec<usize>, Option<String>) {
+        (vec![self.0.results.len()], self.0.next)
+    }
+}
+
 impl Prediction {
     /// Leverage the get url provided, to refresh struct attributes
-    pub async fn reload(&mut self) -> anyhow::Result<()> {
-        let api_key = api_key()?;
-        let endpoint = self.urls.get.clone();
-        let client = reqwest::Client::new();
-        let response = client
-            .get(endpoint)
-            .header("Authorization", format!("Token {api_key}"))
-            .send()
-            .await?;
-
-        let data = response.text().await?;
-        let prediction: Prediction = serde_json::from_str(data.as_str())?;
-        *self = prediction;
-        anyhow::Ok(())
+    pub async fn reload(&mut self) -> ReplicateResult<()> {
+        self.refresh().await
     }
 
-    /// Get the status for the current prediction
-    pub async fn get_status(&mut self) -> PredictionStatus {
-        self.status.clone()
+    /// Cancel the prediction, using the config it was retrieved with, and update the struct
+    /// attributes from Replicate's response
+    pub async fn cancel(&mut self) -> ReplicateResult<()> {
+        let cancel_url = self.urls.cancel.clone();
+        self.update_from(Method::POST, &cancel_url).await
     }
 
-    /// Get the stream from a prediction
-    pub async fn get_stream(
-        &mut self,
-    ) -> anyhow::Result<EventStream<impl futures_lite::stream::Stream<Item = reqwest::Result<Bytes>>>>
-    {
-        if let Some(stream_url) = self.urls.stream.clone() {
-            let api_key = api_key()?;
-            let client = reqwest::Client::new();
-            let stream = client
-                .get(stream_url)
-                .header("Authorization", format!("Token {api_key}"))
-                .header("Accept", "text/event-stream")
-                .send()
-                .await?
-                .bytes_stream()
-                .eventsource();
+    async fn refresh(&mut self) -> ReplicateResult<()> {
+        let get_url = self.urls.get.clone();
+        self.update_from(Method::GET, &get_url).await?;
 
-            return anyhow::Ok(stream);
-        } else {
-            return Err(anyhow!("prediction has no stream url available"));
+        let expired = self
+            .start_deadline
+            .is_some_and(|deadline| SystemTime::now() >= deadline);
+        if self.status == PredictionStatus::Starting && expired {
+            self.cancel().await?;
         }
+        Ok(())
     }
-}
 
-/// A client for interacting with 'predictions' endpoint
-#[derive(Debug)]
-pub struct PredictionClient {
-    config: ReplicateConfig,
-}
+    /// The time by which this prediction must start running, if it was created with
+    /// `PredictionClient::create_with_start_deadline`
+    pub fn start_deadline(&self) -> Option<SystemTime> {
+        self.start_deadline
+    }
 
-#[derive(serde::Serialize)]
-struct PredictionInput {
-    version: String,
-    input: serde_json::Value,
-    stream: bool,
-}
+    /// Replace this prediction with the one returned from `url`, keeping local state
+    async fn update_from(&mut self, method: Method, url: &str) -> ReplicateResult<()> {
+        let data = authorized_send(
+            self.config.as_ref(),
+            authorized_request(self.config.as_ref(), method, url)?,
+        )
+        .await?;
 
-impl PredictionClient {
-    /// Create a new `PredictionClient` based upon a `ReplicateConfig` object
-    pub fn from(config: ReplicateConfig) -> Self {
-        PredictionClient { config }
+        let mut prediction: Prediction =
+            serde_json::from_str(&data).map_err(ReplicateError::from)?;
+        prediction.seen_output_items = self.seen_output_items;
+        prediction.config = self.config.take();
+        prediction.start_deadline = self.start_deadline;
+        *self = prediction;
+        Ok(())
     }
-    /// Create a new prediction
-    pub async fn create(
-        &self,
-        owner: &str,
-        name: &str,
-        input: serde_json::Value,
-        stream: bool,
-    ) -> ReplicateResult<Prediction> {
-        let api_key = self.config.get_api_key()?;
-        let base_url = self.config.get_base_url();
-
-        let model_client = ModelClient::from(self.config.clone());
-        let version = model_client.get_latest_version(owner, name).await?.id;
 
-        let endpoint = format!("{base_url}/predictions");
-        let input = PredictionInput {
-            version,
-            input,
-            stream,
-        };
-        let body = serde_json::to_string(&input)
-            .map_err(|err| ReplicateError::SerializationError(err.to_string()))?;
-        let client = reqwest::Client::new();
-        let response = client
-            .post(endpoint)
-            .header("Authorization", format!("Token {api_key}"))
-            .body(body)
-            .send()
+    /// Poll the prediction until it reaches a terminal status (`Succeeded`, `Failed` or
+    /// `Canceled`), using the default `PredictionPollConfig`, leaving it fully refreshed.
+    ///
+    /// Returns immediately if the prediction is already terminal, and returns the first error
+    /// encountered while polling.
+    pub async fn wait_for_completion(&mut self) -> ReplicateResult<()> {
+        self.wait_with_config(&PredictionPollConfig::default())
             .await
-            .map_err(|err| ReplicateError::ClientError(err.to_string()))?;
+    }
 
-        return match response.status() {
-            reqwest::StatusCode::OK | reqwest::StatusCode::CREATED => {
-                let data = response
-                    .text()
-                    .await
-                    .map_err(|err| ReplicateError::ClientError(err.to_string()))?;
-                let prediction: Prediction = serde_json::from_str(&data)
-                    .map_err(|err| ReplicateError::SerializationError(err.to_string()))?;
+    /// Poll the prediction until it reaches a terminal status, using a custom polling schedule
+    pub async fn wait_with_config(&mut self, config: &PredictionPollConfig) -> ReplicateResult<()> {
+        let mut backoff = PollBackoff::default();
+        while !self.status.is_terminal() {
+            let interval = backoff.next_interval(config, &self.status);
+            Delay::new(interval).await;
+            self.refresh().await?;
+        }
 
-                Ok(prediction)
+        Ok(())
+    }
+
+    /// Reload the prediction and return only the output array items added since the last call.
+    ///
+    /// Returns an empty list while no output is available, and an error if the output is not
+    /// an array.
+    pub async fn new_output_items(&mut self) -> ReplicateResult<Vec<Value>> {
+        self.refresh().await?;
+
+        let items = match &self.output {
+            None => return Ok(Vec::new()),
+            Some(Value::Array(items)) => items,
+            Some(_) => {
+                return Err(ReplicateError::Misc(
+                    "prediction output is not an array".to_string(),
+                ))
             }
-            _ => Err(get_error(
-                response.status(),
-                response
-                    .text()
-                    .await
-                    .map_err(|err| ReplicateError::ClientError(err.to_string()))?
-                    .as_str(),
-            )),
         };
-    }
 
-    /// Get details for an existing prediction
-    pub async fn get(&self, id: String) -> anyhow::Result<Prediction> {
-        let api_key = self.config.get_api_key()?;
-        let base_url = self.config.get_base_url();
-
-        let endpoint = format!("{base_url}/predictions/{id}");
-        let client = reqwest::Client::new();
-        let response = client
-            .get(endpoint)
-            .header("Authorization", format!("Token {api_key}"))
-            .send()
-            .await?;
+        let new_items = items
+            .iter()
+            .skip(self.seen_output_items)
+            .cloned()
+            .collect::<Vec<_>>();
+        self.seen_output_items = items.len();
+        Ok(new_items)
+    }
 
-        let data = response.text().await?;
-        let prediction: Prediction = serde_json::from_str(&data)?;
+    /// Get the status for the current prediction
+    pub async fn get_status(&mut self) -> PredictionStatus {
+        self.status.clone()
+    }
 
-        anyhow::Ok(prediction)
+    /// Poll the prediction, yielding its status each time it changes and ending once a
+    /// terminal status has been yielded. Uses the default `PredictionPollConfig` between polls.
+    pub fn status_changes(self) -> impl Stream<Item = ReplicateResult<PredictionStatus>> {
+        self.status_changes_with_config(PredictionPollConfig::default())
     }
 
-    /// List all existing predictions for the current user
-    pub async fn list(&self) -> anyhow::Result<Predictions> {
-        let api_key = self.config.get_api_key()?;
-        let base_url = self.config.get_base_url();
-
-        let endpoint = format!("{base_url}/predictions");
-        let client = reqwest::Client::new();
-        let response = client
-            .get(endpoint)
-            .header("Authorization", format!("Token {api_key}"))
-            .send()
-            .await?;
+    /// Poll the prediction with a custom polling schedule, yielding its status each time it
+    /// changes. The current status is yielded first, and the stream ends after a terminal
+    /// status or the first error.
+    pub fn status_changes_with_config(
+        self,
+        config: PredictionPollConfig,
+    ) -> impl Stream<Item = ReplicateResult<PredictionStatus>> {
+        let state: Option<(Prediction, Option<PredictionStatus>, PollBackoff)> =
+            Some((self, None, PollBackoff::default()));
+        stream::unfold(state, move |state| {
+            let config = config.clone();
+            async move {
+                let (mut prediction, previous, mut backoff) = state?;
 
-        let data = response.text().await?;
-        let predictions: Predictions = serde_json::from_str(&data)?;
+                if let Some(previous) = previous {
+                    while prediction.status == previous {
+                        let interval = backoff.next_interval(&config, &prediction.status);
+                        Delay::new(interval).await;
+                        if let Err(err) = prediction.refresh().await {
+                            return Some((Err(err), None));
+                        }
+                    }
+                }
 
-        anyhow::Ok(predictions)
+                let status = prediction.status.clone();
+                let next = if status.is_terminal() {
+                    None
+                } else {
+                    Some((prediction, Some(status.clone()), backoff))
+                };
+                Some((Ok(status), next))
+            }
+        })
     }
 
-    /// Cancel an existing prediction
-    pub async fn cancel(&self, id: String) -> anyhow::Result<Prediction> {
-        let api_key = self.config.get_api_key()?;
-        let base_url = self.config.get_base_url();
-        let endpoint = format!("{base_url}/predictions/{id}/cancel");
-        let client = reqwest::Client::new();
-        let response = client
-            .post(endpoint)
-            .header("Authorization", format!("Token {api_key}"))
-            .send()
-            .await?;
+    /// Get the queue and run durations computed from the prediction's timestamps
+    pub fn timeline(&self) -> Timeline {
+        Timeline {
+            queue_time: self
+                .started_at
+                .as_deref()
+                .and_then(|started_at| duration_between(&self.created_at, started_at)),
+            run_time: self
+                .started_at
+                .as_deref()
+                .zip(self.completed_at.as_deref())
+                .and_then(|(started_at, completed_at)| duration_between(started_at, completed_at)),
+        }
+    }
+
+    /// Get the time spent running the model as reported in the prediction's metrics. This is
+    /// the time billed, and may differ from the run time in `timeline`.
+    pub fn predict_time(&self) -> Option<Duration> {
+        let predict_time = self.metrics.as_ref()?.predict_time?;
+        Duration::try_from_secs_f64(predict_time).ok()
+    }
 
-        let data = response.text().await?;
-        let prediction: Prediction = serde_json::from_str(&data)?;
+    /// Get why the prediction failed. This is `None` unless the status is `Failed`, so a
+    /// canceled prediction never has a failure reason.
+    ///
+    /// Structured errors are reduced to their `message` where there is one.
+    pub fn failure_reason(&self) -> Option<String> {
+        if self.status != PredictionStatus::Failed {
+            return None;
+        }
 
-        anyhow::Ok(prediction)
+        Some(match &self.error {
+            Some(Value::String(message)) => message.clone(),
+            Some(error) => match error.get("message") {
+                Some(Value::String(message)) => message.clone(),
+                _ => error.to_string(),
+            },
+            None => "no error was reported".to_string(),
+        })
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use httpmock::prelude::*;
-    use serde_json::json;
+    /// Get all urls provided within the prediction's input, such as uploaded files
+    pub fn input_urls(&self) -> Vec<String> {
+        let mut urls = Vec::new();
+        collect_urls(&self.input, &mut urls);
+        urls
+    }
 
-    use super::*;
+    /// Get all urls provided within the prediction's output, such as generated images
+    pub fn output_urls(&self) -> Vec<String> {
+        let mut urls = Vec::new();
+        if let Some(output) = &self.output {
+            collect_urls(output, &mut urls);
+        }
+        urls
+    }
 
-    #[tokio::test]
-    async fn test_get() {
-        let server = MockServer::start();
+    /// Download the content of every url within the prediction's output, in the order returned
+    /// by `output_urls`.
+    ///
+    /// Redirects, such as to signed storage urls, are followed. A url which has expired or no
+    /// longer exists fails with `ReplicateError::NotFound`.
+    pub async fn download_outputs(&self) -> ReplicateResult<Vec<Vec<u8>>> {
+        let config = self.config.clone().unwrap_or_default();
+        try_join_all(
+            self.output_urls()
+                .iter()
+                .map(|url| config.download(url.as_str())),
+        )
+        .await
+    }
 
-        let prediction_mock = server.mock(|when, then| {
-            when.method(GET).path("/predictions/1234");
-            then.status(200).json_body_obj(&json!(
-                {
-                    "id": "1234",
-                    "model": "replicate/hello-world",
-                    "version": "5c7d5dc6dd8bf75c1acaa8565735e7986bc5b66206b55cca93cb72c9bf15ccaa",
-                    "input": {
-                        "text": "Alice"
-                    },
-                    "logs": "",
-                    "error": null,
-                    "status": "starting",
-                    "created_at": "2023-09-08T16:19:34.765994657Z",
-                    "urls": {
-                        "cancel": "https://api.replicate.com/v1/predictions/1234/cancel",
-                        "get": "https://api.replicate.com/v1/predictions/1234"
-                    }
-                }
-            ));
-        });
+    /// Compare this prediction to another, reporting differences in version, status, input and
+    /// output. Inputs and outputs are compared structurally, so each differing field or array
+    /// item is reported separately.
+    pub fn diff(&self, other: &Prediction) -> PredictionDiff {
+        let mut diff = PredictionDiff::default();
 
-        let client = ReplicateConfig::test(server.base_url()).unwrap();
+        if self.version != other.version {
+            diff.version = Some((self.version.clone(), other.version.clone()));
+        }
+        if self.status != other.status {
+            diff.status = Some((self.status.clone(), other.status.clone()));
+        }
+        diff_values(
+            String::new(),
+            Some(&self.input),
+            Some(&other.input),
+            &mut diff.input,
+        );
+        diff_values(
+            String::new(),
+            self.output.as_ref(),
+            other.output.as_ref(),
+            &mut diff.output,
+        );
 
-        let prediction_client = PredictionClient::from(client);
-        prediction_client.get("1234".to_string()).await.unwrap();
+        diff
+    }
 
-        prediction_mock.assert();
+    /// Wrap the prediction so that it is canceled if the wrapper is dropped before the prediction
+    /// reaches a terminal status, such as when a request handler returns early.
+    ///
+    /// The cancel is fire-and-forget: it is spawned onto the current tokio runtime when the
+    /// wrapper is dropped, its result is ignored, and nothing is sent if the wrapper is dropped
+    /// outside a tokio runtime (including on other async runtimes) or the runtime shuts down
+    /// first. Whether the prediction is terminal is judged from its status when last retrieved.
+    /// Use `CancelOnDrop::into_inner` to keep the prediction running, or `cancel` it explicitly
+    /// where the cancel must be sent.
+    ///
+    /// Requires the `tokio` feature, which is enabled by default.
+    #[cfg(feature = "tokio")]
+    pub fn cancel_on_drop(self) -> CancelOnDrop {
+        CancelOnDrop {
+            prediction: Some(self),
+        }
     }
 
-    #[tokio::test]
-    async fn test_create() {
-        let server = MockServer::start();
+    /// Encode a reference to this prediction as a compact string, such as
+    /// `replicate-prediction:<id>:<get url>`, which can be shared and later turned back into a
+    /// prediction with `Prediction::from_permalink`
+    pub fn to_permalink(&self) -> String {
+        format!("{PERMALINK_PREFIX}:{}:{}", self.id, self.urls.get)
+    }
 
-        server.mock(|when, then| {
-            when.method(POST).path("/predictions");
-            then.status(200).json_body_obj(&json!(
-                {
-                    "id": "gm3qorzdhgbfurvjtvhg6dckhu",
-                    "model": "replicate/hello-world",
-                    "version": "5c7d5dc6dd8bf75c1acaa8565735e7986bc5b66206b55cca93cb72c9bf15ccaa",
-                    "input": {
-                        "text": "Alice"
-                    },
-                    "logs": "",
-                    "error": null,
-                    "status": "starting",
-                    "created_at": "2023-09-08T16:19:34.765994657Z",
-                    "urls": {
-                        "cancel": "https://api.replicate.com/v1/predictions/gm3qorzdhgbfurvjtvhg6dckhu/cancel",
-                        "get": "https://api.replicate.com/v1/predictions/gm3qorzdhgbfurvjtvhg6dckhu"
-                    }
-                }
-            ));
-        });
+    /// Rebuild a prediction from a permalink created by `Prediction::to_permalink`.
+    ///
+    /// Only the id and urls are known, with every other field left empty and the status
+    /// `Unknown`, so `reload` the prediction to fetch its details. The prediction uses `config`
+    /// for later requests, and its url must be under `config`'s base url, so a permalink cannot
+    /// send the api token to another host.
+    pub fn from_permalink(
+        permalink: &str,
+        config: &ReplicateConfig,
+    ) -> ReplicateResult<Prediction> {
+        let invalid = || ReplicateError::InvalidRequest(format!("invalid permalink '{permalink}'"));
 
-        server.mock(|when, then| {
-            when.method(GET)
-                .path("/models/replicate/hello-world/versions");
+        let mut parts = permalink.splitn(3, ':');
+        if parts.next() != Some(PERMALINK_PREFIX) {
+            return Err(invalid());
+        }
+        let id = parts
+            .next()
+            .filter(|id| !id.is_empty())
+            .ok_or_else(invalid)?;
+        let get_url = parts
+            .next()
+            .filter(|url| {
+                url.trim_end_matches('/') == config.endpoint(&format!("predictions/{id}"))
+            })
+            .ok_or_else(invalid)?;
 
-            then.status(200).json_body_obj(&json!({
-                "next": null,
-                "previous": null,
-                "results": [{
-                    "id": "5c7d5dc6dd8bf75c1acaa8565735e7986bc5b66206b55cca93cb72c9bf15ccaa",
-                    "created_at": "2022-04-26T19:29:04.418669Z",
-                    "cog_version": "0.3.0",
-                    "openapi_schema": null
-                }]
-            }));
-        });
+        Ok(Prediction {
+            id: id.to_string(),
+            model: String::new(),
+            version: String::new(),
+            input: Value::Null,
+            status: PredictionStatus::Unknown,
+            created_at: String::new(),
+            started_at: None,
+            completed_at: None,
+            urls: PredictionUrls {
+                cancel: format!("{}/cancel", get_url.trim_end_matches('/')),
+                get: get_url.to_string(),
+                stream: None,
+            },
+            output: None,
+            logs: None,
+            error: None,
+            metrics: None,
+            seen_output_items: 0,
+            config: Some(config.clone()),
+            start_deadline: None,
+        })
+    }
 
-        let client = ReplicateConfig::test(server.base_url()).unwrap();
+    /// Get a named field of the prediction's output, for models which output an object. Returns
+    /// `None` if there is no output, it is not an object, or it has no such field.
+    pub fn output_field(&self, key: &str) -> Option<&Value> {
+        self.output.as_ref()?.as_object()?.get(key)
+    }
 
-        let prediction_client = PredictionClient::from(client);
-        prediction_client
-            .create(
-                "replicate",
-                "hello-world",
-                json!({"text": "This is test input"}),
-                false,
-            )
-            .await
-            .unwrap();
+    /// Deserialize the prediction's output into `T`, such as `Vec<String>` for a model which
+    /// outputs a list of tokens or urls.
+    ///
+    /// Returns `ReplicateError::OutputNotAvailable` if there is no output yet, and
+    /// `ReplicateError::SerializationError` if the output does not match `T`.
+    pub fn output_as<T: DeserializeOwned>(&self) -> ReplicateResult<T> {
+        let output = self
+            .output
+            .as_ref()
+            .ok_or(ReplicateError::OutputNotAvailable(format!(
+                "prediction {} has no output yet, its status is {:?}",
+                self.id, self.status
+            )))?;
+        T::deserialize(output).map_err(ReplicateError::from)
     }
 
-    #[tokio::test]
-    async fn test_list_predictions() {
-        let server = MockServer::start();
+    /// Deserialize a named field of the prediction's output into `T`
+    ///
+    /// Returns `ReplicateError::OutputNotAvailable` if the output has no such field, and
+    /// `ReplicateError::SerializationError` if the field does not match `T`.
+    pub fn output_field_as<T: DeserializeOwned>(&self, key: &str) -> ReplicateResult<T> {
+        let field = self
+            .output_field(key)
+            .ok_or(ReplicateError::OutputNotAvailable(format!(
+                "prediction {} output has no field '{key}'",
+                self.id
+            )))?;
+        T::deserialize(field).map_err(ReplicateError::from)
+    }
 
-        server.mock(|when, then| {
-            when.method(GET).path("/predictions");
-            then.status(200).json_body_obj(&json!(
-                { "next": null,
-                  "previous": null,
-                  "results": [
-                    {
-                        "id": "gm3qorzdhgbfurvjtvhg6dckhu",
-                        "model": "replicate/hello-world",
-                        "version": "5c7d5dc6dd8bf75c1acaa8565735e7986bc5b66206b55cca93cb72c9bf15ccaa",
-                        "input": {
-                            "text": "Alice"
-                        },
-                        "logs": "",
-                        "error": null,
-                        "status": "starting",
-                        "created_at": "2023-09-08T16:19:34.765994657Z",
-                        "urls": {
-                            "cancel": "https://api.replicate.com/v1/predictions/gm3qorzdhgbfurvjtvhg6dckhu/cancel",
-                            "get": "https://api.replicate.com/v1/predictions/gm3qorzdhgbfurvjtvhg6dckhu"
-                        }
-                    },
-                    {
-                        "id": "gm3qorzdhgbfurvjtvhg6dckhu",
-                        "model": "replicate/hello-world",
-                        "version": "5c7d5dc6dd8bf75c1acaa8565735e7986bc5b66206b55cca93cb72c9bf15ccaa",
-                        "input": {
-                            "text": "Alice"
-                        },
-                        "logs": "",
-                        "error": null,
-                        "status": "starting",
-                        "created_at": "2023-09-08T16:19:34.765994657Z",
-                        "urls": {
-                            "cancel": "https://api.replicate.com/v1/predictions/gm3qorzdhgbfurvjtvhg6dckhu/cancel",
-                            "get": "https://api.replicate.com/v1/predictions/gm3qorzdhgbfurvjtvhg6dckhu"
-                        }
-                    }
-                ]}
-            ));
-        });
+    /// Get the prediction's output when it is a single string, regardless of status.
+    ///
+    /// Some text models write their output progressively as a growing string while the
+    /// prediction is `Processing`, so reloading and calling this shows partial text without
+    /// streaming. Whether output is available before the prediction completes is
+    /// model-dependent; many models only set it once they have finished.
+    pub fn partial_output_text(&self) -> Option<String> {
+        match &self.output {
+            Some(Value::String(text)) => Some(text.clone()),
+            _ => None,
+        }
+    }
 
-        let client = ReplicateConfig::test(server.base_url()).unwrap();
+    /// Get the stream from a prediction
+    ///
+    /// Returns `ReplicateError::StreamNotAvailable` if the prediction was not created with
+    /// streaming enabled, if the stream url does not use https (unless allowed with
+    /// `ReplicateConfig::with_insecure_streams`), or if the stream url responds with anything
+    /// other than `text/event-stream` (as can happen behind proxies that rewrite the response).
+    ///
+    /// The returned `StreamHandle` yields the prediction's server sent events, and can be closed
+    /// to end the stream early and optionally cancel the prediction. Events are buffered
+    /// according to `ReplicateConfig::with_stream_buffer`.
+    pub async fn get_stream(&mut self) -> ReplicateResult<StreamHandle> {
+        let stream_url = self
+            .urls
+            .stream
+            .clone()
+            .ok_or(ReplicateError::StreamNotAvailable(
+                "prediction has no stream url available".to_string(),
+            ))?;
 
-        let prediction_client = PredictionClient::from(client);
-        prediction_client.list().await.unwrap();
+        open_stream(self.config.as_ref(), &stream_url, &self.urls.cancel).await
     }
 
-    #[tokio::test]
-    async fn test_create_and_reload() {
-        let server = MockServer::start();
+    /// Stream the prediction, keeping only the most recent `output` event and returning it once
+    /// the stream is `done`.
+    ///
+    /// This suits models which send their full output so far with each event, replacing rather
+    /// than appending to the previous one.
+    pub async fn last_output_from_stream(&mut self) -> ReplicateResult<String> {
+        let mut last_output = String::new();
+        self.consume_stream(|output| {
+            last_output.clear();
+            last_output.push_str(output);
+        })
+        .await?;
+        Ok(last_output)
+    }
 
-        server.mock(|when, then| {
-            when.method(POST).path("/predictions");
-            then.status(200).json_body_obj(&json!(
-                {
-                    "id": "gm3qorzdhgbfurvjtvhg6dckhu",
-                    "model": "replicate/hello-world",
-                    "version": "5c7d5dc6dd8bf75c1acaa8565735e7986bc5b66206b55cca93cb72c9bf15ccaa",
-                    "input": {
-                        "text": "Alice"
-                    },
-                    "logs": "",
-                    "error": null,
-                    "status": "starting",
-                    "created_at": "2023-09-08T16:19:34.765994657Z",
-                    "urls": {
-                        "cancel": "https://api.replicate.com/v1/predictions/gm3qorzdhgbfurvjtvhg6dckhu/cancel",
-                        "get": "https://api.replicate.com/v1/predictions/gm3qorzdhgbfurvjtvhg6dckhu"
-                    }
-                }
-            ));
-        });
+    /// Stream the prediction, concatenating the data of every `output` event in order and
+    /// returning it verbatim once the stream is `done`. Returns an error if an `error` event
+    /// arrives.
+    pub async fn collect_stream(&mut self) -> ReplicateResult<String> {
+        self.collect_stream_with(&CollectStreamOptions::default())
+            .await
+    }
 
-        server.mock(|when, then| {
-            when.method(GET)
-                .path("/models/replicate/hello-world/versions");
+    /// Stream the prediction and collect its output as with `collect_stream`, applying
+    /// `options` to the result.
+    pub async fn collect_stream_with(
+        &mut self,
+        options: &CollectStreamOptions,
+    ) -> ReplicateResult<String> {
+        let mut output = String::new();
+        self.consume_stream(|token| output.push_str(token)).await?;
 
-            then.status(200).json_body_obj(&json!({
-                "next": null,
-                "previous": null,
-                "results": [{
-                    "id": "5c7d5dc6dd8bf75c1acaa8565735e7986bc5b66206b55cca93cb72c9bf15ccaa",
-                    "created_at": "2022-04-26T19:29:04.418669Z",
-                    "cog_version": "0.3.0",
-                    "openapi_schema": null
-                }]
-            }));
-        });
+        if options.trim_trailing_whitespace {
+            output.truncate(output.trim_end().len());
+        }
+        Ok(output)
+    }
 
-        let client = ReplicateConfig::test(server.base_url()).unwrap();
+    /// Stream the prediction, sending each `output` event to `tx` as it arrives, and returning
+    /// the concatenation of all output once the stream is `done`.
+    ///
+    /// Streaming continues if the receiving end of `tx` is dropped, so the full output is still
+    /// returned.
+    pub async fn stream_tee(&mut self, tx: Sender<String>) -> ReplicateResult<String> {
+        let mut output = String::new();
+        self.consume_stream(|token| {
+            output.push_str(token);
+            let _ = tx.send(token.to_string());
+        })
+        .await?;
+        Ok(output)
+    }
 
-        let prediction_client = PredictionClient::from(client);
-        let mut prediction = prediction_client
-            .create(
-                "replicate",
-                "hello-world",
-                json!({"text": "This is test input"}),
-                false,
-            )
-            .await
-            .unwrap();
+    /// Stream the prediction as parsed events, so they can be matched on rather than reading
+    /// server sent event names.
+    ///
+    /// The stream ends after `PredictionEvent::Done`, `PredictionEvent::Error`, or an error
+    /// reading the stream, which is returned as `ReplicateError::ClientError`. Events with an
+    /// unrecognized name are skipped.
+    pub async fn events(
+        &mut self,
+    ) -> ReplicateResult<impl Stream<Item = ReplicateResult<PredictionEvent>>> {
+        Ok(parse_events(self.get_stream().await?))
     }
 
-    #[tokio::test]
-    async fn test_cancel() {
-        let server = MockServer::start();
+    /// Read the prediction's stream until its `done` event, passing the data of each `output`
+    /// event to `on_output`
+    async fn consume_stream(&mut self, mut on_output: impl FnMut(&str)) -> ReplicateResult<()> {
+        let mut events = Box::pin(self.events().await?);
+        while let Some(event) = events.next().await {
+            match event? {
+                PredictionEvent::Output(data) => on_output(&data),
+                PredictionEvent::Error(data) => return Err(ReplicateError::Misc(data)),
+                PredictionEvent::Done => return Ok(()),
+                PredictionEvent::Logs(_) => {}
+            }
+        }
 
-        let prediction_mock = server.mock(|when, then| {
-            when.method(POST).path("/predictions/1234/cancel");
-            then.status(200).json_body_obj(&json!(
-                {
-                    "id": "1234",
-                    "model": "replicate/hello-world",
-                    "version": "5c7d5dc6dd8bf75c1acaa8565735e7986bc5b66206b55cca93cb72c9bf15ccaa",
-                    "input": {
-                        "text": "Alice"
-                    },
-                    "logs": "",
-                    "error": null,
-                    "status": "starting",
-                    "created_at": "2023-09-08T16:19:34.765994657Z",
-                    "urls": {
-                        "cancel": "https://api.replicate.com/v1/predictions/1234/cancel",
-                        "get": "https://api.replicate.com/v1/predictions/1234"
+        Err(ReplicateError::StreamNotAvailable(
+            "stream ended before the prediction was done".to_string(),
+        ))
+    }
+}
+
+/// A prediction which is canceled when dropped before completing, created with
+/// `Prediction::cancel_on_drop`
+///
+/// The cancel is spawned onto the current tokio runtime, so dropping the wrapper outside of
+/// one does nothing.
+#[cfg(feature = "tokio")]
+#[derive(Debug)]
+pub struct CancelOnDrop {
+    prediction: Option<Prediction>,
+}
+
+#[cfg(feature = "tokio")]
+impl CancelOnDrop {
+    /// Take the prediction back out of the wrapper, so it is no longer canceled on drop
+    pub fn into_inner(mut self) -> Prediction {
+        self.prediction
+            .take()
+            .expect("prediction is only taken when consuming the wrapper")
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl Deref for CancelOnDrop {
+    type Target = Prediction;
+
+    fn deref(&self) -> &Prediction {
+        self.prediction
+            .as_ref()
+            .expect("prediction is only taken when consuming the wrapper")
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl DerefMut for CancelOnDrop {
+    fn deref_mut(&mut self) -> &mut Prediction {
+        self.prediction
+            .as_mut()
+            .expect("prediction is only taken when consuming the wrapper")
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        let Some(mut prediction) = self.prediction.take() else {
+            return;
+        };
+        if prediction.status.is_terminal() {
+            return;
+        }
+        // Without a tokio runtime there is nowhere to send the cancel from, so the prediction
+        // is left running, as documented on `Prediction::cancel_on_drop`
+        if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+            runtime.spawn(async move {
+                let _ = prediction.cancel().await;
+            });
+        }
+    }
+}
+
+type StreamEvent = Result<Event, EventStreamError<reqwest::Error>>;
+
+/// Open the server sent event stream at `stream_url`, as returned for a prediction or training
+/// created with streaming enabled. `cancel_url` is used if the stream is closed with `cancel`.
+pub(crate) async fn open_stream(
+    config: Option<&ReplicateConfig>,
+    stream_url: &str,
+    cancel_url: &str,
+) -> ReplicateResult<StreamHandle> {
+    let insecure_streams = config.is_some_and(ReplicateConfig::insecure_streams_allowed);
+    if !stream_url.starts_with("https://") && !insecure_streams {
+        return Err(ReplicateError::StreamNotAvailable(format!(
+            "stream url {stream_url} does not use https, enable insecure streams on the config to allow it"
+        )));
+    }
+
+    let response = authorized_stream_request(config, stream_url)?
+        .header("Accept", "text/event-stream")
+        .send()
+        .await
+        .map_err(ReplicateError::from)?;
+
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+    if !content_type.starts_with("text/event-stream") {
+        return Err(ReplicateError::StreamNotAvailable(format!(
+            "expected a text/event-stream response, received '{content_type}'"
+        )));
+    }
+
+    let events = response.bytes_stream().eventsource();
+    #[cfg(feature = "tokio")]
+    let (events, reader) = read_ahead(events, config.map_or(0, ReplicateConfig::stream_buffer));
+    #[cfg(not(feature = "tokio"))]
+    let events = Box::pin(events);
+    Ok(StreamHandle {
+        events,
+        cancel_url: cancel_url.to_string(),
+        config: config.cloned(),
+        #[cfg(feature = "tokio")]
+        reader,
+    })
+}
+
+type EventStream = Pin<Box<dyn Stream<Item = StreamEvent> + Send>>;
+
+/// Read `events` on a spawned task into a channel holding up to `capacity` events, so that
+/// events keep being received while the consumer is busy. The events are read directly when
+/// `capacity` is zero, or there is no tokio runtime to spawn the task onto.
+#[cfg(feature = "tokio")]
+fn read_ahead(
+    events: impl Stream<Item = StreamEvent> + Send + 'static,
+    capacity: usize,
+) -> (EventStream, Option<ReadAhead>) {
+    let runtime = match tokio::runtime::Handle::try_current() {
+        Ok(runtime) if capacity > 0 => runtime,
+        _ => return (Box::pin(events), None),
+    };
+
+    let (sender, mut receiver) = tokio::sync::mpsc::channel(capacity);
+    let task = runtime.spawn(async move {
+        let mut events = Box::pin(events);
+        while let Some(event) = events.next().await {
+            // The consumer has dropped the stream, so there is nothing left to read for
+            if sender.send(event).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    (
+        Box::pin(stream::poll_fn(move |cx| receiver.poll_recv(cx))),
+        Some(ReadAhead(task)),
+    )
+}
+
+/// A task reading a stream ahead of its consumer, aborted when the stream is dropped so that
+/// the connection is closed
+#[cfg(feature = "tokio")]
+struct ReadAhead(tokio::task::JoinHandle<()>);
+
+#[cfg(feature = "tokio")]
+impl Drop for ReadAhead {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+/// Parse the events of an open stream, ending after `PredictionEvent::Done`,
+/// `PredictionEvent::Error`, or an error reading the stream
+pub(crate) fn parse_events(
+    stream: StreamHandle,
+) -> impl Stream<Item = ReplicateResult<PredictionEvent>> {
+    stream
+        .filter_map(|event| {
+            future::ready(match event {
+                Ok(event) => PredictionEvent::from_event(event).map(Ok),
+                Err(EventStreamError::Transport(err)) => Some(Err(ReplicateError::from(err))),
+                Err(err) => Some(Err(ReplicateError::ClientError(err.to_string(), None))),
+            })
+        })
+        .scan(false, |finished, event| {
+            if *finished {
+                return future::ready(None);
+            }
+            *finished = matches!(
+                event,
+                Err(_) | Ok(PredictionEvent::Done | PredictionEvent::Error(_))
+            );
+            future::ready(Some(event))
+        })
+}
+
+/// An event from a prediction's stream, parsed from its server sent event name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PredictionEvent {
+    /// A chunk of output, such as a token from a language model
+    Output(String),
+    /// A line of logs from the model
+    Logs(String),
+    /// The prediction failed, or the stream could not be read
+    Error(String),
+    /// The prediction has finished, and no further events will be sent
+    Done,
+}
+
+impl PredictionEvent {
+    /// Parse a server sent event, skipping any with an unrecognized name
+    fn from_event(event: Event) -> Option<Self> {
+        match event.event.as_str() {
+            "output" => Some(PredictionEvent::Output(event.data)),
+            "logs" => Some(PredictionEvent::Logs(event.data)),
+            "error" => Some(PredictionEvent::Error(event.data)),
+            "done" => Some(PredictionEvent::Done),
+            _ => None,
+        }
+    }
+}
+
+/// An open stream of server sent events for a prediction
+pub struct StreamHandle {
+    events: EventStream,
+    cancel_url: String,
+    config: Option<ReplicateConfig>,
+    /// Task reading events ahead of the consumer, if the stream is buffered
+    #[cfg(feature = "tokio")]
+    reader: Option<ReadAhead>,
+}
+
+impl StreamHandle {
+    /// Close the stream, aborting the underlying connection. If `cancel` is true, the
+    /// prediction is also canceled so no further work is done server side.
+    pub async fn close(self, cancel: bool) -> ReplicateResult<()> {
+        let StreamHandle {
+            events,
+            cancel_url,
+            config,
+            #[cfg(feature = "tokio")]
+            reader,
+        } = self;
+        #[cfg(feature = "tokio")]
+        drop(reader);
+        drop(events);
+
+        if !cancel {
+            return Ok(());
+        }
+
+        authorized_send(
+            config.as_ref(),
+            authorized_request(config.as_ref(), Method::POST, &cancel_url)?,
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+impl Stream for StreamHandle {
+    type Item = StreamEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.events.as_mut().poll_next(cx)
+    }
+}
+
+impl fmt::Debug for StreamHandle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("StreamHandle")
+            .field("cancel_url", &self.cancel_url)
+            .finish()
+    }
+}
+
+/// Build an authorized request with the config a prediction was retrieved with, falling back
+/// to the REPLICATE_API_KEY environment variable for predictions constructed elsewhere
+fn authorized_request(
+    config: Option<&ReplicateConfig>,
+    method: Method,
+    url: &str,
+) -> ReplicateResult<RequestBuilder> {
+    match config {
+        Some(config) => config.request(method, url),
+        None => ReplicateConfig::from_env()?.request(method, url),
+    }
+}
+
+/// Send a request with the limits and retries of `config`, or none without one
+async fn authorized_send(
+    config: Option<&ReplicateConfig>,
+    request: RequestBuilder,
+) -> ReplicateResult<String> {
+    match config {
+        Some(config) => config.send(request).await,
+        None => send(request, None, None).await,
+    }
+}
+
+fn authorized_stream_request(
+    config: Option<&ReplicateConfig>,
+    url: &str,
+) -> ReplicateResult<RequestBuilder> {
+    match config {
+        Some(config) => config.stream_request(Method::GET, url),
+        None => ReplicateConfig::from_env()?.stream_request(Method::GET, url),
+    }
+}
+
+fn duration_between(start: &str, end: &str) -> Option<Duration> {
+    let start = DateTime::parse_from_rfc3339(start).ok()?;
+    let end = DateTime::parse_from_rfc3339(end).ok()?;
+    (end - start).to_std().ok()
+}
+
+/// Check a webhook events filter before it is sent to Replicate, returning it with duplicates
+/// removed. Returns `ReplicateError::InvalidRequest` naming the first entry which is not one of
+/// `start`, `output`, `logs` or `completed`.
+pub fn validate_webhook_events_filter(events: &[String]) -> ReplicateResult<Vec<String>> {
+    let mut filter: Vec<String> = Vec::new();
+    for event in events {
+        serde_json::from_value::<WebhookEvent>(Value::String(event.clone())).map_err(|_| {
+            ReplicateError::InvalidRequest(format!(
+                "unknown webhook event '{event}', expected one of start, output, logs, completed"
+            ))
+        })?;
+        if !filter.contains(event) {
+            filter.push(event.clone());
+        }
+    }
+    Ok(filter)
+}
+
+fn diff_values(
+    path: String,
+    left: Option<&Value>,
+    right: Option<&Value>,
+    changes: &mut Vec<ValueChange>,
+) {
+    match (left, right) {
+        (Some(Value::Object(left)), Some(Value::Object(right))) => {
+            let mut keys = left.keys().chain(right.keys()).collect::<Vec<_>>();
+            keys.sort();
+            keys.dedup();
+            for key in keys {
+                let escaped = key.replace('~', "~0").replace('/', "~1");
+                diff_values(
+                    format!("{path}/{escaped}"),
+                    left.get(key),
+                    right.get(key),
+                    changes,
+                );
+            }
+        }
+        (Some(Value::Array(left)), Some(Value::Array(right))) => {
+            for index in 0..left.len().max(right.len()) {
+                diff_values(
+                    format!("{path}/{index}"),
+                    left.get(index),
+                    right.get(index),
+                    changes,
+                );
+            }
+        }
+        (left, right) if left != right => changes.push(ValueChange {
+            path,
+            left: left.cloned(),
+            right: right.cloned(),
+        }),
+        _ => {}
+    }
+}
+
+fn collect_urls(value: &Value, urls: &mut Vec<String>) {
+    match value {
+        Value::String(value) if value.starts_with("https://") || value.starts_with("http://") => {
+            urls.push(value.clone());
+        }
+        Value::Array(values) => values.iter().for_each(|value| collect_urls(value, urls)),
+        Value::Object(values) => values.values().for_each(|value| collect_urls(value, urls)),
+        _ => {}
+    }
+}
+
+/// A client for interacting with 'predictions' endpoint
+#[derive(Debug)]
+pub struct PredictionClient {
+    config: ReplicateConfig,
+}
+
+#[derive(serde::Serialize)]
+struct PredictionInput {
+    version: String,
+    input: serde_json::Value,
+    stream: bool,
+    #[serde(flatten)]
+    extra: Map<String, Value>,
+}
+
+impl PredictionClient {
+    /// Create a new `PredictionClient` based upon a `ReplicateConfig` object
+    pub fn from(config: ReplicateConfig) -> Self {
+        PredictionClient { config }
+    }
+    fn attach(&self, mut prediction: Prediction) -> Prediction {
+        prediction.config = Some(self.config.clone());
+        prediction
+    }
+
+    fn attach_all(&self, mut predictions: Predictions) -> Predictions {
+        predictions.results = predictions
+            .results
+            .into_iter()
+            .map(|prediction| self.attach(prediction))
+            .collect();
+        predictions
+    }
+
+    /// Create a new prediction against the latest version of a model
+    ///
+    /// The latest version is looked up first, and any failure to do so is returned as
+    /// `ReplicateError::VersionResolution`, distinct from a failure to create the prediction.
+    /// The lookup is made for every prediction, unless the config reuses recent lookups with
+    /// `ReplicateConfig::with_version_lookup_cache`.
+    pub async fn create(
+        &self,
+        owner: &str,
+        name: &str,
+        input: serde_json::Value,
+        stream: bool,
+    ) -> ReplicateResult<Prediction> {
+        let version = self.resolve_latest_version(owner, name).await?;
+        self.create_with_version(&version.id, input, stream).await
+    }
+
+    /// Create a new prediction on the latest version of a model, first checking `input`
+    /// against the version's input schema with `InputSchema::validate`.
+    ///
+    /// Invalid input fails locally with `ReplicateError::ValidationError`, without creating a
+    /// prediction. The schema comes with the version looked up to create the prediction, so no
+    /// extra request is made. Versions without a schema are not validated.
+    pub async fn create_validated(
+        &self,
+        owner: &str,
+        name: &str,
+        input: serde_json::Value,
+        stream: bool,
+    ) -> ReplicateResult<Prediction> {
+        let version = self.resolve_latest_version(owner, name).await?;
+        if version.openapi_schema.is_object() {
+            version.input_schema()?.validate(&input)?;
+        }
+        self.create_with_version(&version.id, input, stream).await
+    }
+
+    async fn resolve_latest_version(
+        &self,
+        owner: &str,
+        name: &str,
+    ) -> ReplicateResult<ModelVersion> {
+        if !self.config.version_lookup_enabled() {
+            return Err(ReplicateError::InvalidRequest(format!(
+                "latest version lookup is disabled, use create_with_version with a pinned version id for {owner}/{name}"
+            )));
+        }
+
+        let model_client = ModelClient::from(self.config.clone());
+        let version = match self.config.version_lookup_ttl() {
+            Some(ttl) => {
+                model_client
+                    .with_version_cache_ttl(ttl)
+                    .get_latest_version_cached(owner, name)
+                    .await
+            }
+            None => model_client.get_latest_version(owner, name).await,
+        };
+        version.map_err(|err| ReplicateError::VersionResolution {
+            model: format!("{owner}/{name}"),
+            source: Box::new(err),
+        })
+    }
+
+    /// Create a new prediction on the latest version of another model, reusing the input of an
+    /// existing prediction. Streaming is enabled if the template prediction was streamed.
+    pub async fn create_like(
+        &self,
+        template: &Prediction,
+        owner: &str,
+        name: &str,
+    ) -> ReplicateResult<Prediction> {
+        self.create(
+            owner,
+            name,
+            template.input.clone(),
+            template.urls.stream.is_some(),
+        )
+        .await
+    }
+
+    /// Create a new prediction on the latest version of a model which has already been
+    /// retrieved, using its `latest_version` directly rather than looking it up again.
+    /// Returns `ReplicateError::NotFound` if the model has no versions.
+    pub async fn create_for(
+        &self,
+        model: &Model,
+        input: serde_json::Value,
+        stream: bool,
+    ) -> ReplicateResult<Prediction> {
+        let version = model.latest_version.as_ref().ok_or_else(|| {
+            ReplicateError::NotFound(format!(
+                "no versions found for {}/{}",
+                model.owner, model.name
+            ))
+        })?;
+        self.create_with_version(&version.id, input, stream).await
+    }
+
+    /// Create a new prediction against a specific model version id
+    ///
+    /// The version is posted as given, without looking up the model, so a pinned version is
+    /// used even after the model publishes a newer one.
+    pub async fn create_with_version(
+        &self,
+        version_id: &str,
+        input: serde_json::Value,
+        stream: bool,
+    ) -> ReplicateResult<Prediction> {
+        self.create_with_extra(version_id, input, stream, Map::new())
+            .await
+    }
+
+    /// Create a streaming prediction against a specific model version id, returning both its
+    /// events and a future resolving to the completed prediction.
+    ///
+    /// The stream yields events as they arrive and ends after `PredictionEvent::Done` or
+    /// `PredictionEvent::Error`. The future polls the prediction independently of the stream,
+    /// so it resolves with final details such as metrics and logs whether or not the stream is
+    /// consumed.
+    pub async fn run_streaming(
+        &self,
+        version_id: &str,
+        input: serde_json::Value,
+    ) -> ReplicateResult<(
+        impl Stream<Item = PredictionEvent>,
+        impl Future<Output = ReplicateResult<Prediction>>,
+    )> {
+        let mut prediction = self.create_with_version(version_id, input, true).await?;
+        let events = prediction
+            .events()
+            .await?
+            .map(|event| event.unwrap_or_else(|err| PredictionEvent::Error(err.to_string())));
+
+        let completion = async move {
+            prediction.wait_for_completion().await?;
+            Ok(prediction)
+        };
+
+        Ok((events, completion))
+    }
+
+    /// Create a new prediction against a specific model version id, which is canceled if it has
+    /// not started running within `start_within`, to avoid paying for stale work.
+    ///
+    /// Replicate has no field for this, so the deadline is enforced client side: whenever the
+    /// prediction is reloaded (including while waiting on it) and is still `Starting` past its
+    /// deadline, it is canceled. A prediction which is never polled is not canceled. The
+    /// deadline is available from `Prediction::start_deadline`.
+    pub async fn create_with_start_deadline(
+        &self,
+        version_id: &str,
+        input: serde_json::Value,
+        stream: bool,
+        start_within: Duration,
+    ) -> ReplicateResult<Prediction> {
+        let deadline = SystemTime::now() + start_within;
+        let mut prediction = self.create_with_version(version_id, input, stream).await?;
+        prediction.start_deadline = Some(deadline);
+        Ok(prediction)
+    }
+
+    /// Create a new prediction against a specific model version id, which sends a request to
+    /// `webhook` as it progresses, instead of needing to be polled.
+    ///
+    /// Only the listed `events` trigger the webhook. If `events` is empty no filter is sent,
+    /// and Replicate's default applies.
+    pub async fn create_with_webhook(
+        &self,
+        version_id: &str,
+        input: serde_json::Value,
+        stream: bool,
+        webhook: &str,
+        events: &[WebhookEvent],
+    ) -> ReplicateResult<Prediction> {
+        let mut extra = Map::new();
+        extra.insert("webhook".to_string(), Value::from(webhook));
+        if !events.is_empty() {
+            let events = serde_json::to_value(events).map_err(ReplicateError::from)?;
+            extra.insert("webhook_events_filter".to_string(), events);
+        }
+
+        self.create_with_extra(version_id, input, stream, extra)
+            .await
+    }
+
+    /// Create a new prediction against a specific model version id, merging additional
+    /// top-level fields (such as `webhook`) into the request body.
+    ///
+    /// Fields in `extra` named `version`, `input` or `stream` are ignored in favour of the
+    /// explicit arguments. A `webhook_events_filter` is checked before sending, see
+    /// `validate_webhook_events_filter`.
+    pub async fn create_with_extra(
+        &self,
+        version_id: &str,
+        input: serde_json::Value,
+        stream: bool,
+        mut extra: Map<String, Value>,
+    ) -> ReplicateResult<Prediction> {
+        extra.retain(|key, _| !matches!(key.as_str(), "version" | "input" | "stream"));
+        if let Some(filter) = extra.get_mut("webhook_events_filter") {
+            let events = filter
+                .as_array()
+                .ok_or(ReplicateError::InvalidRequest(
+                    "webhook_events_filter must be an array of event names".to_string(),
+                ))?
+                .iter()
+                .map(|event| {
+                    event.as_str().map(str::to_string).ok_or_else(|| {
+                        ReplicateError::InvalidRequest(format!(
+                            "webhook_events_filter entry {event} is not a string"
+                        ))
+                    })
+                })
+                .collect::<ReplicateResult<Vec<_>>>()?;
+            *filter = Value::from(validate_webhook_events_filter(&events)?);
+        }
+
+        let endpoint = self.config.endpoint("predictions");
+        let input = PredictionInput {
+            version: version_id.to_string(),
+            input,
+            stream,
+            extra,
+        };
+        let body = serde_json::to_string(&input).map_err(ReplicateError::from)?;
+        let data = self
+            .config
+            .send(self.config.request(Method::POST, endpoint)?.body(body))
+            .await?;
+
+        let prediction: Prediction = serde_json::from_str(&data).map_err(ReplicateError::from)?;
+        Ok(self.attach(prediction))
+    }
+
+    /// Get details for an existing prediction
+    pub async fn get(&self, id: String) -> ReplicateResult<Prediction> {
+        let endpoint = self.config.endpoint(&format!("predictions/{id}"));
+        let data = self
+            .config
+            .send(self.config.request(Method::GET, endpoint)?)
+            .await?;
+
+        let prediction: Prediction = serde_json::from_str(&data).map_err(ReplicateError::from)?;
+        Ok(self.attach(prediction))
+    }
+
+    /// List all existing predictions for the current user
+    pub async fn list(&self) -> ReplicateResult<Predictions> {
+        let endpoint = self.config.endpoint("predictions");
+        let data = self
+            .config
+            .send(self.config.request(Method::GET, endpoint)?)
+            .await?;
+
+        let predictions: Predictions = serde_json::from_str(&data).map_err(ReplicateError::from)?;
+        Ok(self.attach_all(predictions))
+    }
+
+    /// Fetch a single page of predictions. `None` fetches the first page, and otherwise
+    /// `cursor` may be either a `next` or `previous` url from an earlier page, or the bare
+    /// `cursor` value from one.
+    pub async fn list_page(&self, cursor: Option<&str>) -> ReplicateResult<Predictions> {
+        let endpoint = page_url(&self.config, "predictions", cursor)?;
+        self.fetch_url(&endpoint).await
+    }
+
+    /// List all existing predictions for the current user, following the `next` cursor of
+    /// each page until there are no more.
+    ///
+    /// If `max_pages` is set, at most that many pages are fetched, and any predictions beyond
+    /// them are left out.
+    pub async fn list_all(&self, max_pages: Option<usize>) -> ReplicateResult<Vec<Prediction>> {
+        let endpoint = self.config.endpoint("predictions");
+        collect_pages(endpoint, max_pages, |url| async move {
+            self.fetch_url(&url).await
+        })
+        .await
+    }
+
+    /// Count all existing predictions for the current user
+    ///
+    /// Replicate does not expose a total count, so this walks every page of
+    /// predictions, making one request per page (O(n) in the number of predictions).
+    /// If `max_pages` is set, at most that many pages are counted. Only the count of each page
+    /// is kept, so memory use does not grow with the number of predictions.
+    pub async fn count(&self, max_pages: Option<usize>) -> ReplicateResult<usize> {
+        let endpoint = self.config.endpoint("predictions");
+        let counts = collect_pages(endpoint, max_pages, |url| async move {
+            self.fetch_url(&url).await.map(PageCount)
+        })
+        .await?;
+        Ok(counts.into_iter().sum())
+    }
+
+    /// Fetch a page of predictions from a stored `next` or `previous` cursor url
+    pub async fn fetch_url(&self, endpoint: &str) -> ReplicateResult<Predictions> {
+        check_page_url(&self.config, "predictions", endpoint)?;
+
+        let data = self
+            .config
+            .send(self.config.request(Method::GET, endpoint)?)
+            .await?;
+
+        let predictions: Predictions = serde_json::from_str(&data).map_err(ReplicateError::from)?;
+        Ok(self.attach_all(predictions))
+    }
+
+    /// Cancel an existing prediction
+    pub async fn cancel(&self, id: String) -> ReplicateResult<Prediction> {
+        self.cancel_prediction(&id).await
+    }
+
+    /// List all predictions matching a filter, following every page of results
+    ///
+    /// See `PredictionFilter` for which filters are applied by Replicate and which are
+    /// emulated client side. If `max_pages` is set, at most that many pages are fetched.
+    pub async fn list_filtered(
+        &self,
+        filter: &PredictionFilter,
+        max_pages: Option<usize>,
+    ) -> ReplicateResult<Vec<Prediction>> {
+        let endpoint =
+            reqwest::Url::parse_with_params(&self.config.endpoint("predictions"), filter.query())
+                .map_err(|err| ReplicateError::InvalidRequest(err.to_string()))?;
+
+        let predictions = collect_pages(endpoint.to_string(), max_pages, |url| async move {
+            self.fetch_url(&url).await
+        })
+        .await?;
+
+        Ok(predictions
+            .into_iter()
+            .filter(|prediction| filter.matches(prediction))
+            .collect())
+    }
+
+    /// Cancel all in-flight predictions for a specific model, returning how many were canceled
+    ///
+    /// Every page of predictions is walked to find those for `owner/name` which have not yet
+    /// reached a terminal status, which are then canceled concurrently. If `max_pages` is set,
+    /// only predictions on the first `max_pages` pages are canceled. If any cancellation fails,
+    /// the first error is returned.
+    pub async fn cancel_for_model(
+        &self,
+        owner: &str,
+        name: &str,
+        max_pages: Option<usize>,
+    ) -> ReplicateResult<usize> {
+        let model = format!("{owner}/{name}");
+
+        let ids: Vec<String> = self
+            .list_all(max_pages)
+            .await?
+            .into_iter()
+            .filter(|prediction| prediction.model == model && !prediction.status.is_terminal())
+            .map(|prediction| prediction.id)
+            .collect();
+
+        let canceled = try_join_all(ids.iter().map(|id| self.cancel_prediction(id))).await?;
+        Ok(canceled.len())
+    }
+
+    /// Wait on many predictions concurrently, yielding each one as soon as it reaches a
+    /// terminal status, rather than in the order they were given.
+    ///
+    /// Each prediction is polled with the default `PredictionPollConfig`. A prediction which fails to
+    /// reload is yielded as an error without affecting the others.
+    pub fn join_stream(
+        predictions: Vec<Prediction>,
+    ) -> impl Stream<Item = ReplicateResult<Prediction>> {
+        predictions
+            .into_iter()
+            .map(|mut prediction| async move {
+                prediction.wait_for_completion().await?;
+                Ok(prediction)
+            })
+            .collect::<FuturesUnordered<_>>()
+    }
+
+    async fn cancel_prediction(&self, id: &str) -> ReplicateResult<Prediction> {
+        let endpoint = self.config.endpoint(&format!("predictions/{id}/cancel"));
+        let data = self
+            .config
+            .send(self.config.request(Method::POST, endpoint)?)
+            .await?;
+
+        let prediction: Prediction = serde_json::from_str(&data).map_err(ReplicateError::from)?;
+        Ok(self.attach(prediction))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_lite::StreamExt;
+    use httpmock::prelude::*;
+    use indoc::indoc;
+    use serde_json::json;
+
+    use super::*;
+
+    const API_URL: &str = "https://api.replicate.com/v1";
+
+    /// A `hello-world` prediction with the given `id` and `status`, whose urls are under
+    /// `base_url`
+    fn prediction_json(base_url: &str, id: &str, status: &str) -> Value {
+        json!({
+            "id": id,
+            "model": "replicate/hello-world",
+            "version": "5c7d5dc6dd8bf75c1acaa8565735e7986bc5b66206b55cca93cb72c9bf15ccaa",
+            "input": {
+                "text": "Alice"
+            },
+            "status": status,
+            "created_at": "2023-09-08T16:19:34.765994657Z",
+            "urls": {
+                "cancel": format!("{base_url}/predictions/{id}/cancel"),
+                "get": format!("{base_url}/predictions/{id}")
+            }
+        })
+    }
+
+    #[tokio::test]
+    async fn test_get() {
+        let server = MockServer::start();
+
+        let prediction_mock = server.mock(|when, then| {
+            when.method(GET).path("/predictions/1234");
+            then.status(200)
+                .json_body_obj(&prediction_json(API_URL, "1234", "starting"));
+        });
+
+        let client = ReplicateConfig::test(server.base_url()).unwrap();
+
+        let prediction_client = PredictionClient::from(client);
+        prediction_client.get("1234".to_string()).await.unwrap();
+
+        prediction_mock.assert();
+    }
+
+    #[tokio::test]
+    async fn test_get_typed_errors() {
+        let server = MockServer::start();
+
+        server.mock(|when, then| {
+            when.method(GET).path("/predictions/expired");
+            then.status(401).json_body_obj(&json!({
+                "title": "Unauthenticated",
+                "detail": "You did not pass a valid authentication token"
+            }));
+        });
+        server.mock(|when, then| {
+            when.method(GET).path("/predictions/missing");
+            then.status(404).json_body_obj(&json!({
+                "title": "Not found",
+                "detail": "The requested resource could not be found"
+            }));
+        });
+        server.mock(|when, then| {
+            when.method(GET).path("/predictions");
+            then.status(402).json_body_obj(&json!({
+                "title": "Payment required",
+                "detail": "Add a payment method to continue"
+            }));
+        });
+        server.mock(|when, then| {
+            when.method(POST).path("/predictions/missing/cancel");
+            then.status(404);
+        });
+
+        let client = ReplicateConfig::test(server.base_url()).unwrap();
+        let prediction_client = PredictionClient::from(client);
+
+        let err = prediction_client
+            .get("expired".to_string())
+            .await
+            .unwrap_err();
+        assert!(matches!(err.inner(), ReplicateError::InvalidCredentials(_)));
+        assert_eq!(err.context(), Some(("GET", "/predictions/expired")));
+
+        let err = prediction_client
+            .get("missing".to_string())
+            .await
+            .unwrap_err();
+        assert!(matches!(err.inner(), ReplicateError::NotFound(_)));
+
+        let err = prediction_client.list().await.unwrap_err();
+        assert!(matches!(err.inner(), ReplicateError::PaymentNeeded(_)));
+
+        let err = prediction_client
+            .cancel("missing".to_string())
+            .await
+            .unwrap_err();
+        assert!(matches!(err.inner(), ReplicateError::NotFound(_)));
+    }
+
+    #[tokio::test]
+    async fn test_get_with_base_path() {
+        let server = MockServer::start();
+
+        let prediction_mock = server.mock(|when, then| {
+            when.method(GET).path("/replicate/v1/predictions/1234");
+            then.status(200)
+                .json_body_obj(&prediction_json(API_URL, "1234", "starting"));
+        });
+
+        let client = ReplicateConfig::test(format!("{}/replicate/v1/", server.base_url())).unwrap();
+
+        let prediction_client = PredictionClient::from(client);
+        prediction_client.get("1234".to_string()).await.unwrap();
+
+        prediction_mock.assert();
+    }
+
+    #[tokio::test]
+    async fn test_create() {
+        let server = MockServer::start();
+
+        server.mock(|when, then| {
+            when.method(POST).path("/predictions");
+            then.status(200).json_body_obj(&prediction_json(
+                API_URL,
+                "gm3qorzdhgbfurvjtvhg6dckhu",
+                "starting",
+            ));
+        });
+
+        server.mock(|when, then| {
+            when.method(GET).path("/models/replicate/hello-world");
+
+            then.status(200).json_body_obj(&json!({
+                "latest_version": {
+                    "id": "5c7d5dc6dd8bf75c1acaa8565735e7986bc5b66206b55cca93cb72c9bf15ccaa",
+                    "created_at": "2022-04-26T19:29:04.418669Z",
+                    "cog_version": "0.3.0",
+                    "openapi_schema": null
+                }
+            }));
+        });
+
+        let client = ReplicateConfig::test(server.base_url()).unwrap();
+
+        let prediction_client = PredictionClient::from(client);
+        prediction_client
+            .create(
+                "replicate",
+                "hello-world",
+                json!({"text": "This is test input"}),
+                false,
+            )
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_create_validated() {
+        let server = MockServer::start();
+
+        let prediction_mock = server.mock(|when, then| {
+            when.method(POST)
+                .path("/predictions")
+                .json_body_partial(r#"{"input": {"text": "Alice"}}"#);
+            then.status(201).json_body_obj(&prediction_json(
+                API_URL,
+                "gm3qorzdhgbfurvjtvhg6dckhu",
+                "starting",
+            ));
+        });
+
+        let model_mock = server.mock(|when, then| {
+            when.method(GET).path("/models/replicate/hello-world");
+            then.status(200).json_body_obj(&json!({
+                "latest_version": {
+                    "id": "5c7d5dc6dd8bf75c1acaa8565735e7986bc5b66206b55cca93cb72c9bf15ccaa",
+                    "created_at": "2022-04-26T19:29:04.418669Z",
+                    "cog_version": "0.3.0",
+                    "openapi_schema": {
+                        "components": {
+                            "schemas": {
+                                "Input": {
+                                    "type": "object",
+                                    "required": ["text"],
+                                    "properties": {
+                                        "text": {"type": "string"}
+                                    }
+                                }
+                            }
+                        }
                     }
                 }
+            }));
+        });
+
+        let config = ReplicateConfig::test(server.base_url()).unwrap();
+        let prediction_client = PredictionClient::from(config);
+
+        let err = prediction_client
+            .create_validated("replicate", "hello-world", json!({"text": 1}), false)
+            .await
+            .unwrap_err();
+        match err {
+            ReplicateError::ValidationError { invalid_fields, .. } => {
+                assert_eq!(invalid_fields, vec!["input.text"]);
+            }
+            _ => panic!("expected a validation error, got {err:?}"),
+        }
+        prediction_mock.assert_hits(0);
+
+        prediction_client
+            .create_validated("replicate", "hello-world", json!({"text": "Alice"}), false)
+            .await
+            .unwrap();
+        prediction_mock.assert_hits(1);
+        model_mock.assert_hits(2);
+
+        // Once opted in, the latest version is reused after the first lookup
+        let config = ReplicateConfig::test(server.base_url())
+            .unwrap()
+            .with_version_lookup_cache(Some(Duration::from_secs(60)));
+        let prediction_client = PredictionClient::from(config);
+        for _ in 0..2 {
+            prediction_client
+                .create_validated("replicate", "hello-world", json!({"text": "Alice"}), false)
+                .await
+                .unwrap();
+        }
+        prediction_mock.assert_hits(3);
+        model_mock.assert_hits(3);
+    }
+
+    #[tokio::test]
+    async fn test_create_without_version_lookup() {
+        let server = MockServer::start();
+
+        let prediction_mock = server.mock(|when, then| {
+            when.method(POST).path("/predictions").json_body_partial(
+                r#"{"version": "5c7d5dc6dd8bf75c1acaa8565735e7986bc5b66206b55cca93cb72c9bf15ccaa"}"#,
+            );
+            then.status(201).json_body_obj(&prediction_json(
+                API_URL,
+                "gm3qorzdhgbfurvjtvhg6dckhu",
+                "starting",
+            ));
+        });
+
+        let model_mock = server.mock(|when, then| {
+            when.method(GET).path("/models/replicate/hello-world");
+            then.status(200);
+        });
+
+        let config = ReplicateConfig::test(server.base_url())
+            .unwrap()
+            .with_version_lookup(false);
+        let prediction_client = PredictionClient::from(config);
+
+        let prediction = prediction_client
+            .create("replicate", "hello-world", json!({"text": "Alice"}), false)
+            .await;
+        assert!(matches!(prediction, Err(ReplicateError::InvalidRequest(_))));
+
+        prediction_client
+            .create_with_version(
+                "5c7d5dc6dd8bf75c1acaa8565735e7986bc5b66206b55cca93cb72c9bf15ccaa",
+                json!({"text": "Alice"}),
+                false,
+            )
+            .await
+            .unwrap();
+
+        prediction_mock.assert();
+        model_mock.assert_hits(0);
+    }
+
+    #[tokio::test]
+    async fn test_create_with_version() {
+        let server = MockServer::start();
+
+        let prediction_mock = server.mock(|when, then| {
+            when.method(POST).path("/predictions").json_body(json!({
+                "version": "5c7d5dc6dd8bf75c1acaa8565735e7986bc5b66206b55cca93cb72c9bf15ccaa",
+                "input": {"text": "Alice"},
+                "stream": false
+            }));
+            then.status(201).json_body_obj(&prediction_json(
+                API_URL,
+                "gm3qorzdhgbfurvjtvhg6dckhu",
+                "starting",
+            ));
+        });
+
+        let models_mock = server.mock(|when, then| {
+            when.path_contains("/models/");
+            then.status(200);
+        });
+
+        let config = ReplicateConfig::test(server.base_url()).unwrap();
+        let prediction_client = PredictionClient::from(config);
+
+        let prediction = prediction_client
+            .create_with_version(
+                "5c7d5dc6dd8bf75c1acaa8565735e7986bc5b66206b55cca93cb72c9bf15ccaa",
+                json!({"text": "Alice"}),
+                false,
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            prediction.version,
+            "5c7d5dc6dd8bf75c1acaa8565735e7986bc5b66206b55cca93cb72c9bf15ccaa"
+        );
+
+        prediction_mock.assert();
+        models_mock.assert_hits(0);
+    }
+
+    #[tokio::test]
+    async fn test_list_predictions() {
+        let server = MockServer::start();
+
+        let prediction = prediction_json(API_URL, "gm3qorzdhgbfurvjtvhg6dckhu", "starting");
+        server.mock(|when, then| {
+            when.method(GET).path("/predictions");
+            then.status(200).json_body_obj(&json!({
+                "next": null,
+                "previous": null,
+                "results": [prediction, prediction]
+            }));
+        });
+
+        let client = ReplicateConfig::test(server.base_url()).unwrap();
+
+        let prediction_client = PredictionClient::from(client);
+        prediction_client.list().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_count_predictions() {
+        let server = MockServer::start();
+
+        let prediction = prediction_json(API_URL, "gm3qorzdhgbfurvjtvhg6dckhu", "succeeded");
+
+        let second_page = server.mock(|when, then| {
+            when.method(GET)
+                .path("/predictions")
+                .query_param("cursor", "abc");
+            then.status(200).json_body_obj(&json!({
+                "next": null,
+                "previous": null,
+                "results": [prediction]
+            }));
+        });
+
+        let first_page = server.mock(|when, then| {
+            when.method(GET).path("/predictions");
+            then.status(200).json_body_obj(&json!({
+                "next": format!("{}/predictions?cursor=abc", server.base_url()),
+                "previous": null,
+                "results": [prediction, prediction]
+            }));
+        });
+
+        let client = ReplicateConfig::test(server.base_url()).unwrap();
+
+        let prediction_client = PredictionClient::from(client);
+        let count = prediction_client.count(None).await.unwrap();
+        assert_eq!(count, 3);
+
+        let count = prediction_client.count(Some(1)).await.unwrap();
+        assert_eq!(count, 2);
+
+        first_page.assert_hits(2);
+        second_page.assert();
+    }
+
+    #[tokio::test]
+    async fn test_list_all_and_list_page() {
+        let server = MockServer::start();
+
+        let prediction = |id: &str| prediction_json(API_URL, id, "succeeded");
+
+        let second_page = server.mock(|when, then| {
+            when.method(GET)
+                .path("/predictions")
+                .query_param("cursor", "abc");
+            then.status(200).json_body_obj(&json!({
+                "next": null,
+                "previous": format!("{}/predictions", server.base_url()),
+                "results": [prediction("3")]
+            }));
+        });
+
+        let first_page = server.mock(|when, then| {
+            when.method(GET).path("/predictions");
+            then.status(200).json_body_obj(&json!({
+                "next": format!("{}/predictions?cursor=abc", server.base_url()),
+                "previous": null,
+                "results": [prediction("1"), prediction("2")]
+            }));
+        });
+
+        let client = ReplicateConfig::test(server.base_url()).unwrap();
+        let prediction_client = PredictionClient::from(client);
+
+        let ids = |predictions: &[Prediction]| -> Vec<String> {
+            predictions
+                .iter()
+                .map(|prediction| prediction.id.clone())
+                .collect()
+        };
+
+        let predictions = prediction_client.list_all(None).await.unwrap();
+        assert_eq!(ids(&predictions), vec!["1", "2", "3"]);
+        first_page.assert_hits(1);
+        second_page.assert_hits(1);
+
+        let predictions = prediction_client.list_all(Some(1)).await.unwrap();
+        assert_eq!(ids(&predictions), vec!["1", "2"]);
+        first_page.assert_hits(2);
+        second_page.assert_hits(1);
+
+        let page = prediction_client.list_page(None).await.unwrap();
+        assert_eq!(ids(&page.results), vec!["1", "2"]);
+
+        let page = prediction_client
+            .list_page(page.next.as_deref())
+            .await
+            .unwrap();
+        assert_eq!(ids(&page.results), vec!["3"]);
+        assert!(page.next.is_none());
+
+        let page = prediction_client.list_page(Some("abc")).await.unwrap();
+        assert_eq!(ids(&page.results), vec!["3"]);
+        second_page.assert_hits(3);
+    }
+
+    #[test]
+    fn test_input_urls() {
+        let mut prediction = prediction_json(API_URL, "1234", "succeeded");
+        prediction["input"] = json!({
+            "text": "Alice",
+            "image": "https://replicate.delivery/pbxt/image.png",
+            "masks": ["https://replicate.delivery/pbxt/mask.png", "not a url"],
+            "steps": 20
+        });
+        let prediction: Prediction = serde_json::from_value(prediction).unwrap();
+
+        let mut urls = prediction.input_urls();
+        urls.sort();
+        assert_eq!(
+            urls,
+            vec![
+                "https://replicate.delivery/pbxt/image.png".to_string(),
+                "https://replicate.delivery/pbxt/mask.png".to_string(),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_download_outputs() {
+        let server = MockServer::start();
+
+        let redirect_mock = server.mock(|when, then| {
+            when.method(GET).path("/pbxt/output.png");
+            then.status(302)
+                .header("Location", server.url("/signed/output.png"));
+        });
+        let content_mock = server.mock(|when, then| {
+            when.method(GET).path("/signed/output.png");
+            then.status(200).body("image bytes");
+        });
+        let expired_mock = server.mock(|when, then| {
+            when.method(GET).path("/pbxt/expired.png");
+            then.status(403).body("<Error>Request has expired</Error>");
+        });
+
+        let prediction = |output: Value| -> Prediction {
+            let mut prediction = prediction_json(API_URL, "1234", "succeeded");
+            prediction["output"] = output;
+            let mut prediction: Prediction = serde_json::from_value(prediction).unwrap();
+            prediction.config = Some(ReplicateConfig::test(server.base_url()).unwrap());
+            prediction
+        };
+
+        let outputs = prediction(json!([server.url("/pbxt/output.png")]))
+            .download_outputs()
+            .await
+            .unwrap();
+        assert_eq!(outputs, vec![b"image bytes".to_vec()]);
+        redirect_mock.assert();
+        content_mock.assert();
+
+        let err = prediction(json!(server.url("/pbxt/expired.png")))
+            .download_outputs()
+            .await
+            .unwrap_err();
+        assert!(matches!(err.inner(), ReplicateError::NotFound(_)));
+        assert_eq!(err.context(), Some(("GET", "/pbxt/expired.png")));
+        expired_mock.assert();
+    }
+
+    #[test]
+    fn test_diff() {
+        let prediction = |version: &str, status: &str, output: Value| -> Prediction {
+            let mut prediction = prediction_json(API_URL, "1234", status);
+            prediction["version"] = json!(version);
+            prediction["input"] = json!({"text": "Alice", "seed": 1});
+            prediction["output"] = output;
+            serde_json::from_value(prediction).unwrap()
+        };
+
+        let left = prediction("v1", "succeeded", json!(["hello", " Alice"]));
+        assert!(left.diff(&left).is_empty());
+
+        let mut right = prediction("v2", "failed", json!(["hello", " Bob", "!"]));
+        right.input = json!({"text": "Alice", "seed": 2});
+
+        assert_eq!(
+            left.diff(&right),
+            PredictionDiff {
+                version: Some(("v1".to_string(), "v2".to_string())),
+                status: Some((PredictionStatus::Succeeded, PredictionStatus::Failed)),
+                input: vec![ValueChange {
+                    path: "/seed".to_string(),
+                    left: Some(json!(1)),
+                    right: Some(json!(2)),
+                }],
+                output: vec![
+                    ValueChange {
+                        path: "/1".to_string(),
+                        left: Some(json!(" Alice")),
+                        right: Some(json!(" Bob")),
+                    },
+                    ValueChange {
+                        path: "/2".to_string(),
+                        left: None,
+                        right: Some(json!("!")),
+                    },
+                ],
+            }
+        );
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_cancel_on_drop() {
+        let server = MockServer::start();
+
+        let prediction = |id: &str, status: &str| prediction_json(&server.base_url(), id, status);
+
+        let running_mock = server.mock(|when, then| {
+            when.method(POST).path("/predictions/running/cancel");
+            then.status(200)
+                .json_body_obj(&prediction("running", "canceled"));
+        });
+        let kept_mock = server.mock(|when, then| {
+            when.method(POST).path("/predictions/kept/cancel");
+            then.status(200)
+                .json_body_obj(&prediction("kept", "canceled"));
+        });
+        let done_mock = server.mock(|when, then| {
+            when.method(POST).path("/predictions/done/cancel");
+            then.status(200)
+                .json_body_obj(&prediction("done", "canceled"));
+        });
+
+        let config = ReplicateConfig::test(server.base_url()).unwrap();
+        let load = |id: &str, status: &str| {
+            let mut prediction: Prediction =
+                serde_json::from_value(prediction(id, status)).unwrap();
+            prediction.config = Some(config.clone());
+            prediction
+        };
+
+        let running = load("running", "processing").cancel_on_drop();
+        assert_eq!(running.id, "running");
+        drop(running);
+
+        let kept = load("kept", "processing").cancel_on_drop().into_inner();
+        drop(load("done", "succeeded").cancel_on_drop());
+
+        for _ in 0..100 {
+            if running_mock.hits() > 0 {
+                break;
+            }
+            Delay::new(Duration::from_millis(10)).await;
+        }
+        running_mock.assert();
+        kept_mock.assert_hits(0);
+        done_mock.assert_hits(0);
+        assert_eq!(kept.status, PredictionStatus::Processing);
+    }
+
+    #[tokio::test]
+    async fn test_permalink() {
+        let server = MockServer::start();
+
+        let get_mock = server.mock(|when, then| {
+            when.method(GET)
+                .path("/predictions/1234")
+                .header("Authorization", "Token test-api-key");
+            then.status(200).json_body_obj(&prediction_json(
+                &server.base_url(),
+                "1234",
+                "succeeded",
+            ));
+        });
+
+        let permalink = format!(
+            "replicate-prediction:1234:{}/predictions/1234",
+            server.base_url()
+        );
+        let config = ReplicateConfig::test(server.base_url()).unwrap();
+        let mut prediction = Prediction::from_permalink(&permalink, &config).unwrap();
+        assert_eq!(prediction.id, "1234");
+        assert_eq!(prediction.status, PredictionStatus::Unknown);
+        assert_eq!(
+            prediction.urls.cancel,
+            format!("{}/predictions/1234/cancel", server.base_url())
+        );
+        assert_eq!(prediction.to_permalink(), permalink);
+
+        prediction.reload().await.unwrap();
+        assert_eq!(prediction.model, "replicate/hello-world");
+        assert_eq!(prediction.status, PredictionStatus::Succeeded);
+        get_mock.assert();
+
+        for invalid in [
+            "1234",
+            "replicate-prediction:1234",
+            "replicate-prediction::https://api.replicate.com/v1/predictions/",
+            "replicate-prediction:1234:https://api.replicate.com/v1/predictions/5678",
+            "other:1234:https://api.replicate.com/v1/predictions/1234",
+            "replicate-prediction:1234:https://example.com/v1/predictions/1234",
+            &format!(
+                "replicate-prediction:1234:https://example.com/{}/predictions/1234",
+                server.base_url()
+            ),
+        ] {
+            assert!(matches!(
+                Prediction::from_permalink(invalid, &config),
+                Err(ReplicateError::InvalidRequest(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_output_field() {
+        let mut prediction = prediction_json(API_URL, "1234", "succeeded");
+        prediction["output"] =
+            json!({"image": "https://replicate.delivery/pbxt/image.png", "seed": 42});
+        let mut prediction: Prediction = serde_json::from_value(prediction).unwrap();
+
+        assert_eq!(
+            prediction.output_field("image"),
+            Some(&json!("https://replicate.delivery/pbxt/image.png"))
+        );
+        assert_eq!(prediction.output_field("mask"), None);
+        assert_eq!(prediction.output_field_as::<u64>("seed").unwrap(), 42);
+        assert!(matches!(
+            prediction.output_field_as::<u64>("image"),
+            Err(ReplicateError::SerializationError(..))
+        ));
+        assert!(matches!(
+            prediction.output_field_as::<u64>("mask"),
+            Err(ReplicateError::OutputNotAvailable(_))
+        ));
+
+        prediction.output = Some(json!("not an object"));
+        assert_eq!(prediction.output_field("image"), None);
+    }
+
+    #[test]
+    fn test_failure_reason() {
+        let prediction = |status: &str, error: Value| -> Prediction {
+            let mut prediction = prediction_json(API_URL, "1234", status);
+            prediction["error"] = error;
+            serde_json::from_value(prediction).unwrap()
+        };
+
+        assert_eq!(
+            prediction("failed", json!("CUDA out of memory")).failure_reason(),
+            Some("CUDA out of memory".to_string())
+        );
+        assert_eq!(
+            prediction(
+                "failed",
+                json!({"message": "NSFW content detected", "code": 1})
+            )
+            .failure_reason(),
+            Some("NSFW content detected".to_string())
+        );
+        assert_eq!(
+            prediction("failed", Value::Null).failure_reason(),
+            Some("no error was reported".to_string())
+        );
+        assert_eq!(prediction("canceled", Value::Null).failure_reason(), None);
+        assert_eq!(
+            prediction("succeeded", json!("ignored")).failure_reason(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_predict_time() {
+        let mut prediction = prediction_json(API_URL, "1234", "succeeded");
+        prediction["started_at"] = json!("2023-09-08T16:19:34.779176Z");
+        prediction["completed_at"] = json!("2023-09-08T16:19:35.029285Z");
+        prediction["metrics"] = json!({"predict_time": 0.25});
+        let mut prediction: Prediction = serde_json::from_value(prediction).unwrap();
+
+        assert_eq!(
+            prediction.metrics,
+            Some(PredictionMetrics {
+                predict_time: Some(0.25)
+            })
+        );
+        assert_eq!(prediction.predict_time(), Some(Duration::from_millis(250)));
+
+        prediction.metrics = Some(PredictionMetrics::default());
+        assert_eq!(prediction.predict_time(), None);
+        prediction.metrics = None;
+        assert_eq!(prediction.predict_time(), None);
+    }
+
+    #[test]
+    fn test_output_as() {
+        let mut prediction = prediction_json(API_URL, "1234", "processing");
+        prediction["output"] = json!(null);
+        let mut prediction: Prediction = serde_json::from_value(prediction).unwrap();
+
+        assert!(matches!(
+            prediction.output_as::<Vec<String>>(),
+            Err(ReplicateError::OutputNotAvailable(_))
+        ));
+
+        prediction.output = Some(json!(["Hello", ", ", "Alice"]));
+        let tokens: Vec<String> = prediction.output_as().unwrap();
+        assert_eq!(tokens, vec!["Hello", ", ", "Alice"]);
+
+        prediction.output = Some(json!("https://replicate.delivery/pbxt/image.png"));
+        let url: String = prediction.output_as().unwrap();
+        assert_eq!(url, "https://replicate.delivery/pbxt/image.png");
+        assert!(matches!(
+            prediction.output_as::<Vec<String>>(),
+            Err(ReplicateError::SerializationError(..))
+        ));
+    }
+
+    #[test]
+    fn test_partial_output_text() {
+        let mut prediction: Prediction =
+            serde_json::from_value(prediction_json(API_URL, "1234", "processing")).unwrap();
+
+        assert_eq!(prediction.partial_output_text(), None);
+
+        prediction.output = Some(json!("hello Al"));
+        assert_eq!(
+            prediction.partial_output_text(),
+            Some("hello Al".to_string())
+        );
+
+        prediction.output = Some(json!(["hello", " Alice"]));
+        assert_eq!(prediction.partial_output_text(), None);
+    }
+
+    #[tokio::test]
+    async fn test_stream_not_event_stream() {
+        let server = MockServer::start();
+        std::env::set_var("REPLICATE_API_KEY", "test-api-key");
+
+        let stream_mock = server.mock(|when, then| {
+            when.method(GET).path("/stream/1234");
+            then.status(200)
+                .json_body_obj(&json!({"detail": "not a stream"}));
+        });
+
+        let mut prediction = prediction_json(API_URL, "1234", "starting");
+        prediction["urls"]["stream"] = json!(format!("{}/stream/1234", server.base_url()));
+        let mut prediction: Prediction = serde_json::from_value(prediction).unwrap();
+
+        let stream = prediction.get_stream().await;
+        assert!(matches!(stream, Err(ReplicateError::StreamNotAvailable(_))));
+        stream_mock.assert_hits(0);
+
+        prediction.config = Some(
+            ReplicateConfig::test(server.base_url())
+                .unwrap()
+                .with_insecure_streams(true),
+        );
+        let stream = prediction.get_stream().await;
+        assert!(matches!(stream, Err(ReplicateError::StreamNotAvailable(_))));
+        stream_mock.assert();
+    }
+
+    #[tokio::test]
+    async fn test_fetch_url() {
+        let server = MockServer::start();
+
+        let page_mock = server.mock(|when, then| {
+            when.method(GET)
+                .path("/predictions")
+                .query_param("cursor", "abc");
+            then.status(200).json_body_obj(&json!({
+                "next": null,
+                "previous": format!("{}/predictions", server.base_url()),
+                "results": []
+            }));
+        });
+
+        let client = ReplicateConfig::test(server.base_url()).unwrap();
+        let prediction_client = PredictionClient::from(client);
+
+        let page = prediction_client
+            .fetch_url(&format!("{}/predictions?cursor=abc", server.base_url()))
+            .await
+            .unwrap();
+        assert!(page.results.is_empty());
+        page_mock.assert();
+
+        let invalid = prediction_client
+            .fetch_url("https://example.com/predictions?cursor=abc")
+            .await;
+        assert!(matches!(invalid, Err(ReplicateError::InvalidRequest(_))));
+
+        let invalid = prediction_client
+            .fetch_url(&format!("{}/models", server.base_url()))
+            .await;
+        assert!(matches!(invalid, Err(ReplicateError::InvalidRequest(_))));
+    }
+
+    #[test]
+    fn test_timeline() {
+        let mut prediction = prediction_json(API_URL, "1234", "processing");
+        prediction["started_at"] = json!("2023-09-08T16:19:36.265994657Z");
+        let mut prediction: Prediction = serde_json::from_value(prediction).unwrap();
+
+        assert_eq!(
+            prediction.timeline(),
+            Timeline {
+                queue_time: Some(Duration::from_millis(1500)),
+                run_time: None,
+            }
+        );
+
+        prediction.completed_at = Some("2023-09-08T16:19:39.265994657Z".to_string());
+        assert_eq!(
+            prediction.timeline().run_time,
+            Some(Duration::from_millis(3000))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_create_and_stream() {
+        let server = MockServer::start();
+        std::env::set_var("REPLICATE_API_KEY", "test-api-key");
+
+        server.mock(|when, then| {
+            when.method(GET).path("/models/replicate/hello-world");
+            then.status(200).json_body_obj(&json!({
+                "latest_version": {
+                    "id": "5c7d5dc6dd8bf75c1acaa8565735e7986bc5b66206b55cca93cb72c9bf15ccaa",
+                    "created_at": "2022-04-26T19:29:04.418669Z",
+                    "cog_version": "0.3.0",
+                    "openapi_schema": null
+                }
+            }));
+        });
+
+        let mut prediction = prediction_json(API_URL, "gm3qorzdhgbfurvjtvhg6dckhu", "starting");
+        prediction["urls"]["stream"] = json!(format!(
+            "{}/stream/gm3qorzdhgbfurvjtvhg6dckhu",
+            server.base_url()
+        ));
+
+        server.mock(|when, then| {
+            when.method(POST).path("/predictions");
+            then.status(201).json_body_obj(&prediction);
+        });
+
+        let stream_mock = server.mock(|when, then| {
+            when.method(GET)
+                .path("/stream/gm3qorzdhgbfurvjtvhg6dckhu")
+                .header("Accept", "text/event-stream");
+            then.status(200)
+                .header("Content-Type", "text/event-stream")
+                .body(indoc! {"
+                    event: output
+                    id: 1
+                    data: Hello
+
+                    event: output
+                    id: 2
+                    data: , world
+
+                    event: logs
+                    id: 3
+                    data: generated 2 tokens
+
+                    event: done
+                    id: 4
+                    data: {}
+
+                "});
+        });
+
+        let config = ReplicateConfig::test(server.base_url())
+            .unwrap()
+            .with_insecure_streams(true);
+        let prediction_client = PredictionClient::from(config);
+        let mut prediction = prediction_client
+            .create("replicate", "hello-world", json!({"text": "Alice"}), true)
+            .await
+            .unwrap();
+
+        let mut stream = prediction.get_stream().await.unwrap();
+        let mut output = String::new();
+        let mut done = false;
+        while let Some(event) = stream.next().await {
+            let event = event.unwrap();
+            match event.event.as_str() {
+                "output" => output.push_str(&event.data),
+                "done" => {
+                    done = true;
+                    break;
+                }
+                _ => {}
+            }
+        }
+
+        assert!(done);
+        assert_eq!(output, "Hello, world");
+        stream_mock.assert();
+    }
+
+    #[tokio::test]
+    async fn test_run_streaming() {
+        let server = MockServer::start();
+
+        let mut prediction =
+            prediction_json(&server.base_url(), "gm3qorzdhgbfurvjtvhg6dckhu", "starting");
+        prediction["urls"]["stream"] = json!(format!(
+            "{}/stream/gm3qorzdhgbfurvjtvhg6dckhu",
+            server.base_url()
+        ));
+
+        let create_mock = server.mock(|when, then| {
+            when.method(POST)
+                .path("/predictions")
+                .json_body_partial(r#"{"stream": true}"#);
+            then.status(201).json_body_obj(&prediction);
+        });
+
+        server.mock(|when, then| {
+            when.method(GET).path("/stream/gm3qorzdhgbfurvjtvhg6dckhu");
+            then.status(200)
+                .header("Content-Type", "text/event-stream")
+                .body(indoc! {"
+                    event: output
+                    id: 1
+                    data: Hello
+
+                    event: logs
+                    id: 2
+                    data: generated 1 token
+
+                    event: output
+                    id: 3
+                    data: , world
+
+                    event: done
+                    id: 4
+                    data: {}
+
+                    event: output
+                    id: 5
+                    data: ignored
+
+                "});
+        });
+
+        let mut completed = prediction.clone();
+        completed["status"] = json!("succeeded");
+        completed["output"] = json!(["Hello", ", world"]);
+
+        let get_mock = server.mock(|when, then| {
+            when.method(GET)
+                .path("/predictions/gm3qorzdhgbfurvjtvhg6dckhu");
+            then.status(200).json_body_obj(&completed);
+        });
+
+        let config = ReplicateConfig::test(server.base_url())
+            .unwrap()
+            .with_insecure_streams(true);
+        let prediction_client = PredictionClient::from(config);
+        let (events, completion) = prediction_client
+            .run_streaming(
+                "5c7d5dc6dd8bf75c1acaa8565735e7986bc5b66206b55cca93cb72c9bf15ccaa",
+                json!({"text": "Alice"}),
+            )
+            .await
+            .unwrap();
+
+        let events: Vec<PredictionEvent> = events.collect().await;
+        assert_eq!(
+            events,
+            vec![
+                PredictionEvent::Output("Hello".to_string()),
+                PredictionEvent::Logs("generated 1 token".to_string()),
+                PredictionEvent::Output(", world".to_string()),
+                PredictionEvent::Done,
+            ]
+        );
+
+        let prediction = completion.await.unwrap();
+        assert_eq!(prediction.status, PredictionStatus::Succeeded);
+        assert_eq!(prediction.output, Some(json!(["Hello", ", world"])));
+
+        create_mock.assert();
+        get_mock.assert();
+    }
+
+    #[tokio::test]
+    async fn test_create_and_reload() {
+        let server = MockServer::start();
+
+        server.mock(|when, then| {
+            when.method(POST).path("/predictions");
+            then.status(200).json_body_obj(&prediction_json(
+                API_URL,
+                "gm3qorzdhgbfurvjtvhg6dckhu",
+                "starting",
+            ));
+        });
+
+        server.mock(|when, then| {
+            when.method(GET).path("/models/replicate/hello-world");
+
+            then.status(200).json_body_obj(&json!({
+                "latest_version": {
+                    "id": "5c7d5dc6dd8bf75c1acaa8565735e7986bc5b66206b55cca93cb72c9bf15ccaa",
+                    "created_at": "2022-04-26T19:29:04.418669Z",
+                    "cog_version": "0.3.0",
+                    "openapi_schema": null
+                }
+            }));
+        });
+
+        let client = ReplicateConfig::test(server.base_url()).unwrap();
+
+        let prediction_client = PredictionClient::from(client);
+        let _prediction = prediction_client
+            .create(
+                "replicate",
+                "hello-world",
+                json!({"text": "This is test input"}),
+                false,
+            )
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_cancel() {
+        let server = MockServer::start();
+
+        let prediction_mock = server.mock(|when, then| {
+            when.method(POST).path("/predictions/1234/cancel");
+            then.status(200)
+                .json_body_obj(&prediction_json(API_URL, "1234", "starting"));
+        });
+
+        let config = ReplicateConfig::test(server.base_url()).unwrap();
+        let prediction_client = PredictionClient::from(config);
+
+        prediction_client.cancel("1234".to_string()).await.unwrap();
+
+        prediction_mock.assert();
+    }
+
+    #[tokio::test]
+    async fn test_cancel_for_model() {
+        let server = MockServer::start();
+
+        let prediction = |id: &str, model: &str, status: &str| {
+            let mut prediction = prediction_json(API_URL, id, status);
+            prediction["model"] = json!(model);
+            prediction
+        };
+
+        server.mock(|when, then| {
+            when.method(GET).path("/predictions");
+            then.status(200).json_body_obj(&json!({
+                "next": null,
+                "previous": null,
+                "results": [
+                    prediction("1", "replicate/hello-world", "processing"),
+                    prediction("2", "replicate/hello-world", "succeeded"),
+                    prediction("3", "replicate/other-model", "starting"),
+                    prediction("4", "replicate/hello-world", "starting"),
+                ]
+            }));
+        });
+
+        let cancel_one = server.mock(|when, then| {
+            when.method(POST).path("/predictions/1/cancel");
+            then.status(200)
+                .json_body_obj(&prediction("1", "replicate/hello-world", "canceled"));
+        });
+        let cancel_four = server.mock(|when, then| {
+            when.method(POST).path("/predictions/4/cancel");
+            then.status(200)
+                .json_body_obj(&prediction("4", "replicate/hello-world", "canceled"));
+        });
+        let cancel_other = server.mock(|when, then| {
+            when.method(POST)
+                .path_matches(Regex::new("/predictions/[23]/cancel").unwrap());
+            then.status(200);
+        });
+
+        let config = ReplicateConfig::test(server.base_url()).unwrap();
+        let prediction_client = PredictionClient::from(config);
+
+        let canceled = prediction_client
+            .cancel_for_model("replicate", "hello-world", None)
+            .await
+            .unwrap();
+
+        assert_eq!(canceled, 2);
+        cancel_one.assert();
+        cancel_four.assert();
+        cancel_other.assert_hits(0);
+    }
+
+    #[test]
+    fn test_poll_config_interval() {
+        let config = PredictionPollConfig {
+            initial_interval: Duration::from_secs(1),
+            max_interval: Duration::from_secs(10),
+            backoff_multiplier: 2.0,
+            processing_interval: Duration::from_millis(250),
+        };
+
+        let starting = PredictionStatus::Starting;
+        assert_eq!(config.interval(&starting, 0), Duration::from_secs(1));
+        assert_eq!(config.interval(&starting, 2), Duration::from_secs(4));
+        assert_eq!(config.interval(&starting, 10), Duration::from_secs(10));
+
+        let processing = PredictionStatus::Processing;
+        assert_eq!(config.interval(&processing, 0), Duration::from_millis(250));
+        assert_eq!(config.interval(&processing, 2), Duration::from_secs(1));
+        assert_eq!(config.interval(&processing, 10), Duration::from_secs(10));
+        assert_eq!(
+            config.interval(&processing, u32::MAX),
+            Duration::from_secs(10)
+        );
+
+        // Each status backs off from its own first interval
+        let mut backoff = PollBackoff::default();
+        backoff.next_interval(&config, &starting);
+        backoff.next_interval(&config, &starting);
+        assert_eq!(
+            backoff.next_interval(&config, &processing),
+            Duration::from_millis(250)
+        );
+        assert_eq!(
+            backoff.next_interval(&config, &processing),
+            Duration::from_millis(500)
+        );
+        assert_eq!(
+            backoff.next_interval(&config, &starting),
+            Duration::from_secs(4)
+        );
+
+        assert_eq!(
+            PredictionPollConfig::default().interval(&processing, 0),
+            Duration::from_millis(500)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_wait_processing_cadence() {
+        let server = MockServer::start();
+
+        let get_mock = server.mock(|when, then| {
+            when.method(GET).path("/predictions/1234");
+            then.status(200).json_body_obj(&prediction_json(
+                &server.base_url(),
+                "1234",
+                "succeeded",
+            ));
+        });
+
+        let mut prediction: Prediction =
+            serde_json::from_value(prediction_json(&server.base_url(), "1234", "processing"))
+                .unwrap();
+        prediction.config = Some(ReplicateConfig::test(server.base_url()).unwrap());
+
+        // A processing prediction is first polled after processing_interval, however long the
+        // starting interval is
+        let poll_config = PredictionPollConfig {
+            initial_interval: Duration::from_secs(60),
+            max_interval: Duration::from_secs(60),
+            backoff_multiplier: 2.0,
+            processing_interval: Duration::from_millis(1),
+        };
+        let started = std::time::Instant::now();
+        prediction.wait_with_config(&poll_config).await.unwrap();
+
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert_eq!(prediction.status, PredictionStatus::Succeeded);
+        get_mock.assert();
+    }
+
+    #[tokio::test]
+    async fn test_wait() {
+        let server = MockServer::start();
+        std::env::set_var("REPLICATE_API_KEY", "test-api-key");
+
+        let prediction = |status: &str| {
+            let mut prediction = prediction_json(&server.base_url(), "1234", status);
+            if status == "succeeded" {
+                prediction["output"] = json!("hello Alice");
+            }
+            prediction
+        };
+
+        let get_mock = server.mock(|when, then| {
+            when.method(GET).path("/predictions/1234");
+            then.status(200).json_body_obj(&prediction("succeeded"));
+        });
+
+        let mut prediction: Prediction = serde_json::from_value(prediction("starting")).unwrap();
+        let poll_config = PredictionPollConfig {
+            initial_interval: Duration::from_millis(1),
+            max_interval: Duration::from_millis(5),
+            backoff_multiplier: 2.0,
+            processing_interval: Duration::from_millis(1),
+        };
+        prediction.wait_with_config(&poll_config).await.unwrap();
+
+        assert_eq!(prediction.status, PredictionStatus::Succeeded);
+        assert_eq!(prediction.output, Some(json!("hello Alice")));
+        get_mock.assert();
+    }
+
+    #[tokio::test]
+    async fn test_join_stream() {
+        let server = MockServer::start();
+        std::env::set_var("REPLICATE_API_KEY", "test-api-key");
+
+        let prediction = |id: &str, status: &str| prediction_json(&server.base_url(), id, status);
+
+        let slow_mock = server.mock(|when, then| {
+            when.method(GET).path("/predictions/slow");
+            then.status(200)
+                .json_body_obj(&prediction("slow", "succeeded"));
+        });
+
+        let predictions = vec![
+            serde_json::from_value(prediction("slow", "processing")).unwrap(),
+            serde_json::from_value(prediction("done", "succeeded")).unwrap(),
+        ];
+        let completed = PredictionClient::join_stream(predictions)
+            .map(|prediction| prediction.unwrap().id)
+            .collect::<Vec<_>>()
+            .await;
+
+        assert_eq!(completed, vec!["done".to_string(), "slow".to_string()]);
+        slow_mock.assert();
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_completion() {
+        let server = MockServer::start();
+        std::env::set_var("REPLICATE_API_KEY", "test-api-key");
+
+        let prediction = |id: &str, status: &str| prediction_json(&server.base_url(), id, status);
+
+        let done_mock = server.mock(|when, then| {
+            when.method(GET).path("/predictions/done");
+            then.status(200)
+                .json_body_obj(&prediction("done", "succeeded"));
+        });
+        server.mock(|when, then| {
+            when.method(GET).path("/predictions/missing");
+            then.status(404).json_body_obj(&json!({
+                "title": "Not found",
+                "detail": "Not found."
+            }));
+        });
+
+        let mut done: Prediction = serde_json::from_value(prediction("done", "failed")).unwrap();
+        done.wait_for_completion().await.unwrap();
+        assert_eq!(done.status, PredictionStatus::Failed);
+        done_mock.assert_hits(0);
+
+        let mut missing: Prediction =
+            serde_json::from_value(prediction("missing", "processing")).unwrap();
+        let err = missing.wait_for_completion().await.unwrap_err();
+        assert!(matches!(err.inner(), ReplicateError::NotFound(_)));
+    }
+
+    #[tokio::test]
+    async fn test_status_changes() {
+        let server = MockServer::start();
+        std::env::set_var("REPLICATE_API_KEY", "test-api-key");
+
+        let prediction = |status: &str| prediction_json(&server.base_url(), "1234", status);
+
+        let poll_config = PredictionPollConfig {
+            initial_interval: Duration::from_millis(1),
+            max_interval: Duration::from_millis(5),
+            backoff_multiplier: 2.0,
+            processing_interval: Duration::from_millis(1),
+        };
+        let starting: Prediction = serde_json::from_value(prediction("starting")).unwrap();
+        let mut changes = Box::pin(starting.status_changes_with_config(poll_config));
+
+        assert_eq!(
+            changes.next().await.unwrap().unwrap(),
+            PredictionStatus::Starting
+        );
+
+        let mut processing_mock = server.mock(|when, then| {
+            when.method(GET).path("/predictions/1234");
+            then.status(200).json_body_obj(&prediction("processing"));
+        });
+        assert_eq!(
+            changes.next().await.unwrap().unwrap(),
+            PredictionStatus::Processing
+        );
+        processing_mock.delete();
+
+        server.mock(|when, then| {
+            when.method(GET).path("/predictions/1234");
+            then.status(200).json_body_obj(&prediction("succeeded"));
+        });
+        assert_eq!(
+            changes.next().await.unwrap().unwrap(),
+            PredictionStatus::Succeeded
+        );
+        assert!(changes.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_create_version_resolution_error() {
+        let server = MockServer::start();
+
+        server.mock(|when, then| {
+            when.method(GET).path("/models/replicate/hello-world");
+            then.status(502).json_body_obj(&json!({
+                "title": "Bad gateway",
+                "detail": "The server is temporarily unavailable"
+            }));
+        });
+        let prediction_mock = server.mock(|when, then| {
+            when.method(POST).path("/predictions");
+            then.status(201);
+        });
+
+        let config = ReplicateConfig::test(server.base_url()).unwrap();
+        let prediction_client = PredictionClient::from(config);
+        let err = prediction_client
+            .create("replicate", "hello-world", json!({"text": "Alice"}), false)
+            .await
+            .unwrap_err();
+
+        assert!(matches!(err, ReplicateError::VersionResolution { .. }));
+        assert!(matches!(err.inner(), ReplicateError::HttpStatus(502, _)));
+        assert_eq!(
+            err.context(),
+            Some(("GET", "/models/replicate/hello-world"))
+        );
+        assert!(err
+            .to_string()
+            .starts_with("failed to resolve latest version of replicate/hello-world"));
+        prediction_mock.assert_hits(0);
+    }
+
+    #[tokio::test]
+    async fn test_create_for() {
+        let server = MockServer::start();
+
+        let model_mock = server.mock(|when, then| {
+            when.method(GET).path("/models/replicate/hello-world");
+            then.status(500);
+        });
+        let prediction_mock = server.mock(|when, then| {
+            when.method(POST).path("/predictions").json_body(json!({
+                "version": "5c7d5dc6dd8bf75c1acaa8565735e7986bc5b66206b55cca93cb72c9bf15ccaa",
+                "input": {"text": "Alice"},
+                "stream": false
+            }));
+            then.status(201).json_body_obj(&prediction_json(
+                API_URL,
+                "gm3qorzdhgbfurvjtvhg6dckhu",
+                "starting",
+            ));
+        });
+
+        let model: Model = serde_json::from_value(json!({
+            "url": "https://replicate.com/replicate/hello-world",
+            "owner": "replicate",
+            "name": "hello-world",
+            "description": "A tiny model that says hello",
+            "visibility": "public",
+            "github_url": "https://github.com/replicate/cog-examples",
+            "paper_url": null,
+            "license_url": null,
+            "run_count": 5681081,
+            "cover_image_url": "...",
+            "default_example": null,
+            "latest_version": {
+                "id": "5c7d5dc6dd8bf75c1acaa8565735e7986bc5b66206b55cca93cb72c9bf15ccaa",
+                "created_at": "2022-04-26T19:29:04.418669Z",
+                "cog_version": "0.3.0",
+                "openapi_schema": {}
+            }
+        }))
+        .unwrap();
+
+        let config = ReplicateConfig::test(server.base_url()).unwrap();
+        let prediction_client = PredictionClient::from(config);
+        prediction_client
+            .create_for(&model, json!({"text": "Alice"}), false)
+            .await
+            .unwrap();
+
+        prediction_mock.assert();
+        model_mock.assert_hits(0);
+    }
+
+    #[tokio::test]
+    async fn test_create_with_start_deadline() {
+        let server = MockServer::start();
+
+        let prediction = |status: &str| prediction_json(&server.base_url(), "1234", status);
+
+        server.mock(|when, then| {
+            when.method(POST).path("/predictions");
+            then.status(201).json_body_obj(&prediction("starting"));
+        });
+        let get_mock = server.mock(|when, then| {
+            when.method(GET).path("/predictions/1234");
+            then.status(200).json_body_obj(&prediction("starting"));
+        });
+        let cancel_mock = server.mock(|when, then| {
+            when.method(POST).path("/predictions/1234/cancel");
+            then.status(200).json_body_obj(&prediction("canceled"));
+        });
+
+        let config = ReplicateConfig::test(server.base_url()).unwrap();
+        let prediction_client = PredictionClient::from(config);
+        let mut prediction = prediction_client
+            .create_with_start_deadline(
+                "5c7d5dc6dd8bf75c1acaa8565735e7986bc5b66206b55cca93cb72c9bf15ccaa",
+                json!({"text": "Alice"}),
+                false,
+                Duration::ZERO,
+            )
+            .await
+            .unwrap();
+        assert!(prediction.start_deadline().is_some());
+
+        prediction.reload().await.unwrap();
+
+        assert_eq!(prediction.status, PredictionStatus::Canceled);
+        assert!(prediction.start_deadline().is_some());
+        get_mock.assert();
+        cancel_mock.assert();
+    }
+
+    #[tokio::test]
+    async fn test_create_with_extra() {
+        let server = MockServer::start();
+
+        let prediction_mock = server.mock(|when, then| {
+            when.method(POST).path("/predictions").json_body(json!({
+                "version": "5c7d5dc6dd8bf75c1acaa8565735e7986bc5b66206b55cca93cb72c9bf15ccaa",
+                "input": {"text": "Alice"},
+                "stream": false,
+                "webhook": "https://example.com/webhook",
+                "webhook_events_filter": ["completed"]
+            }));
+            then.status(201).json_body_obj(&prediction_json(
+                API_URL,
+                "gm3qorzdhgbfurvjtvhg6dckhu",
+                "starting",
             ));
         });
 
         let config = ReplicateConfig::test(server.base_url()).unwrap();
         let prediction_client = PredictionClient::from(config);
 
-        prediction_client.cancel("1234".to_string()).await.unwrap();
+        let mut extra = Map::new();
+        extra.insert("webhook".to_string(), json!("https://example.com/webhook"));
+        extra.insert(
+            "webhook_events_filter".to_string(),
+            json!(["completed", "completed"]),
+        );
+        extra.insert("stream".to_string(), json!(true));
+
+        prediction_client
+            .create_with_extra(
+                "5c7d5dc6dd8bf75c1acaa8565735e7986bc5b66206b55cca93cb72c9bf15ccaa",
+                json!({"text": "Alice"}),
+                false,
+                extra,
+            )
+            .await
+            .unwrap();
+
+        prediction_mock.assert();
+    }
+
+    #[tokio::test]
+    async fn test_create_with_webhook() {
+        let server = MockServer::start();
+
+        let response = prediction_json(API_URL, "gm3qorzdhgbfurvjtvhg6dckhu", "starting");
+        let filtered_mock = server.mock(|when, then| {
+            when.method(POST).path("/predictions").json_body(json!({
+                "version": "5c7d5dc6dd8bf75c1acaa8565735e7986bc5b66206b55cca93cb72c9bf15ccaa",
+                "input": {"text": "Alice"},
+                "stream": false,
+                "webhook": "https://example.com/webhook",
+                "webhook_events_filter": ["start", "completed"]
+            }));
+            then.status(201).json_body_obj(&response);
+        });
+        let unfiltered_mock = server.mock(|when, then| {
+            when.method(POST).path("/predictions").json_body(json!({
+                "version": "5c7d5dc6dd8bf75c1acaa8565735e7986bc5b66206b55cca93cb72c9bf15ccaa",
+                "input": {"text": "Alice"},
+                "stream": false,
+                "webhook": "https://example.com/webhook"
+            }));
+            then.status(201).json_body_obj(&response);
+        });
+
+        let config = ReplicateConfig::test(server.base_url()).unwrap();
+        let prediction_client = PredictionClient::from(config);
+
+        prediction_client
+            .create_with_webhook(
+                "5c7d5dc6dd8bf75c1acaa8565735e7986bc5b66206b55cca93cb72c9bf15ccaa",
+                json!({"text": "Alice"}),
+                false,
+                "https://example.com/webhook",
+                &[WebhookEvent::Start, WebhookEvent::Completed],
+            )
+            .await
+            .unwrap();
+        prediction_client
+            .create_with_webhook(
+                "5c7d5dc6dd8bf75c1acaa8565735e7986bc5b66206b55cca93cb72c9bf15ccaa",
+                json!({"text": "Alice"}),
+                false,
+                "https://example.com/webhook",
+                &[],
+            )
+            .await
+            .unwrap();
+
+        filtered_mock.assert();
+        unfiltered_mock.assert();
+    }
+
+    #[tokio::test]
+    async fn test_create_with_invalid_webhook_events_filter() {
+        let server = MockServer::start();
+
+        let prediction_mock = server.mock(|when, then| {
+            when.method(POST).path("/predictions");
+            then.status(201);
+        });
+
+        let config = ReplicateConfig::test(server.base_url()).unwrap();
+        let prediction_client = PredictionClient::from(config);
+
+        for filter in [json!(["start", "finished"]), json!("completed"), json!([1])] {
+            let mut extra = Map::new();
+            extra.insert("webhook_events_filter".to_string(), filter);
+
+            let prediction = prediction_client
+                .create_with_extra(
+                    "5c7d5dc6dd8bf75c1acaa8565735e7986bc5b66206b55cca93cb72c9bf15ccaa",
+                    json!({"text": "Alice"}),
+                    false,
+                    extra,
+                )
+                .await;
+            assert!(matches!(prediction, Err(ReplicateError::InvalidRequest(_))));
+        }
+
+        prediction_mock.assert_hits(0);
+    }
+
+    #[test]
+    fn test_validate_webhook_events_filter() {
+        let events = ["start", "completed", "start"].map(str::to_string);
+        assert_eq!(
+            validate_webhook_events_filter(&events).unwrap(),
+            vec!["start".to_string(), "completed".to_string()]
+        );
+
+        let events = ["start", "finished"].map(str::to_string);
+        match validate_webhook_events_filter(&events) {
+            Err(ReplicateError::InvalidRequest(message)) => assert!(message.contains("finished")),
+            other => panic!("expected InvalidRequest, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_list_filtered() {
+        let server = MockServer::start();
+
+        let prediction = |id: &str, model: &str, status: &str| {
+            let mut prediction = prediction_json(API_URL, id, status);
+            prediction["model"] = json!(model);
+            prediction
+        };
+
+        let list_mock = server.mock(|when, then| {
+            when.method(GET)
+                .path("/predictions")
+                .query_param("created_after", "2023-09-01T00:00:00Z");
+            then.status(200).json_body_obj(&json!({
+                "next": null,
+                "previous": null,
+                "results": [
+                    prediction("1", "replicate/hello-world", "succeeded"),
+                    prediction("2", "replicate/hello-world", "failed"),
+                    prediction("3", "replicate/other-model", "succeeded"),
+                ]
+            }));
+        });
+
+        let config = ReplicateConfig::test(server.base_url()).unwrap();
+        let prediction_client = PredictionClient::from(config);
+
+        let filter = PredictionFilter {
+            created_after: Some("2023-09-01T00:00:00Z".to_string()),
+            status: Some(PredictionStatus::Succeeded),
+            model: Some("replicate/hello-world".to_string()),
+            ..Default::default()
+        };
+        let predictions = prediction_client
+            .list_filtered(&filter, None)
+            .await
+            .unwrap();
+
+        assert_eq!(predictions.len(), 1);
+        assert_eq!(predictions[0].id, "1");
+        list_mock.assert();
+    }
+
+    #[tokio::test]
+    async fn test_request_hook() {
+        let server = MockServer::start();
+
+        let prediction_mock = server.mock(|when, then| {
+            when.method(GET)
+                .path("/predictions/1234")
+                .header("Authorization", "Token test-api-key")
+                .header("X-Proxy-Token", "secret");
+            then.status(200)
+                .json_body_obj(&prediction_json(API_URL, "1234", "starting"));
+        });
+
+        let config = ReplicateConfig::test(server.base_url())
+            .unwrap()
+            .with_request_hook(|request| request.header("X-Proxy-Token", "secret"));
+        let prediction_client = PredictionClient::from(config);
+        prediction_client.get("1234".to_string()).await.unwrap();
 
         prediction_mock.assert();
     }
+
+    #[tokio::test]
+    async fn test_prediction_cancel() {
+        let server = MockServer::start();
+
+        let prediction = |status: &str| prediction_json(&server.base_url(), "1234", status);
+
+        let get_mock = server.mock(|when, then| {
+            when.method(GET).path("/predictions/1234");
+            then.status(200).json_body_obj(&prediction("processing"));
+        });
+        let cancel_mock = server.mock(|when, then| {
+            when.method(POST)
+                .path("/predictions/1234/cancel")
+                .header("Authorization", "Token test-api-key")
+                .header("X-Proxy-Token", "secret");
+            then.status(200).json_body_obj(&prediction("canceled"));
+        });
+
+        let config = ReplicateConfig::test(server.base_url())
+            .unwrap()
+            .with_request_hook(|request| request.header("X-Proxy-Token", "secret"));
+        let prediction_client = PredictionClient::from(config);
+        let mut prediction = prediction_client.get("1234".to_string()).await.unwrap();
+        prediction.cancel().await.unwrap();
+
+        assert_eq!(prediction.status, PredictionStatus::Canceled);
+        assert!(prediction.config.is_some());
+        get_mock.assert();
+        cancel_mock.assert();
+    }
+
+    #[test]
+    fn test_unknown_status() {
+        let status: PredictionStatus = serde_json::from_value(json!("queued")).unwrap();
+
+        assert_eq!(status, PredictionStatus::Unknown);
+        assert!(!status.is_terminal());
+        assert!(!status.is_running());
+    }
+
+    #[test]
+    fn test_status_helpers() {
+        for status in [PredictionStatus::Starting, PredictionStatus::Processing] {
+            assert!(status.is_running());
+            assert!(!status.is_terminal());
+        }
+        for status in [
+            PredictionStatus::Succeeded,
+            PredictionStatus::Failed,
+            PredictionStatus::Canceled,
+        ] {
+            assert!(status.is_terminal());
+            assert!(!status.is_running());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_new_output_items() {
+        let server = MockServer::start();
+        std::env::set_var("REPLICATE_API_KEY", "test-api-key");
+
+        let prediction = |output: Value| {
+            let mut prediction = prediction_json(&server.base_url(), "1234", "processing");
+            prediction["output"] = output;
+            prediction
+        };
+
+        let mut prediction_mock = server.mock(|when, then| {
+            when.method(GET).path("/predictions/1234");
+            then.status(200)
+                .json_body_obj(&prediction(json!(["a.png", "b.png"])));
+        });
+
+        let mut current: Prediction = serde_json::from_value(prediction(json!(null))).unwrap();
+        assert_eq!(
+            current.new_output_items().await.unwrap(),
+            vec![json!("a.png"), json!("b.png")]
+        );
+        prediction_mock.delete();
+
+        prediction_mock = server.mock(|when, then| {
+            when.method(GET).path("/predictions/1234");
+            then.status(200)
+                .json_body_obj(&prediction(json!(["a.png", "b.png", "c.png"])));
+        });
+        assert_eq!(
+            current.new_output_items().await.unwrap(),
+            vec![json!("c.png")]
+        );
+        prediction_mock.delete();
+
+        server.mock(|when, then| {
+            when.method(GET).path("/predictions/1234");
+            then.status(200)
+                .json_body_obj(&prediction(json!("not an array")));
+        });
+        assert!(current.new_output_items().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_create_like() {
+        let server = MockServer::start();
+
+        server.mock(|when, then| {
+            when.method(GET).path("/models/replicate/other-model");
+            then.status(200).json_body_obj(&json!({
+                "latest_version": {
+                    "id": "abcd",
+                    "created_at": "2022-04-26T19:29:04.418669Z",
+                    "cog_version": "0.3.0",
+                    "openapi_schema": null
+                }
+            }));
+        });
+
+        let mut prediction = prediction_json(API_URL, "5678", "starting");
+        prediction["model"] = json!("replicate/other-model");
+        prediction["version"] = json!("abcd");
+
+        let create_mock = server.mock(|when, then| {
+            when.method(POST).path("/predictions").json_body(json!({
+                "version": "abcd",
+                "input": {"text": "Alice"},
+                "stream": false
+            }));
+            then.status(201).json_body_obj(&prediction);
+        });
+
+        let template: Prediction =
+            serde_json::from_value(prediction_json(API_URL, "1234", "succeeded")).unwrap();
+
+        let config = ReplicateConfig::test(server.base_url()).unwrap();
+        let prediction_client = PredictionClient::from(config);
+        let prediction = prediction_client
+            .create_like(&template, "replicate", "other-model")
+            .await
+            .unwrap();
+
+        assert_eq!(prediction.model, "replicate/other-model");
+        create_mock.assert();
+    }
+
+    #[tokio::test]
+    async fn test_close_stream() {
+        let server = MockServer::start();
+        std::env::set_var("REPLICATE_API_KEY", "test-api-key");
+
+        server.mock(|when, then| {
+            when.method(GET).path("/stream/1234");
+            then.status(200)
+                .header("Content-Type", "text/event-stream")
+                .body(indoc! {"
+                    event: output
+                    id: 1
+                    data: Hello
+
+                "});
+        });
+
+        let cancel_mock = server.mock(|when, then| {
+            when.method(POST).path("/predictions/1234/cancel");
+            then.status(200);
+        });
+
+        let mut prediction = prediction_json(&server.base_url(), "1234", "processing");
+        prediction["urls"]["stream"] = json!(format!("{}/stream/1234", server.base_url()));
+        let mut prediction: Prediction = serde_json::from_value(prediction).unwrap();
+        prediction.config = Some(
+            ReplicateConfig::test(server.base_url())
+                .unwrap()
+                .with_insecure_streams(true),
+        );
+
+        let mut stream = prediction.get_stream().await.unwrap();
+        assert_eq!(stream.next().await.unwrap().unwrap().data, "Hello");
+
+        stream.close(true).await.unwrap();
+        cancel_mock.assert();
+    }
+
+    #[tokio::test]
+    async fn test_last_output_from_stream() {
+        let server = MockServer::start();
+
+        server.mock(|when, then| {
+            when.method(GET).path("/stream/1234");
+            then.status(200)
+                .header("Content-Type", "text/event-stream")
+                .body(indoc! {"
+                    event: output
+                    id: 1
+                    data: Hel
+
+                    event: output
+                    id: 2
+                    data: Hello
+
+                    event: logs
+                    id: 3
+                    data: generated 2 tokens
+
+                    event: done
+                    id: 4
+                    data: {}
+
+                "});
+        });
+
+        let mut prediction = prediction_json(&server.base_url(), "1234", "processing");
+        prediction["urls"]["stream"] = json!(format!("{}/stream/1234", server.base_url()));
+        let mut prediction: Prediction = serde_json::from_value(prediction).unwrap();
+        prediction.config = Some(
+            ReplicateConfig::test(server.base_url())
+                .unwrap()
+                .with_insecure_streams(true),
+        );
+
+        assert_eq!(prediction.last_output_from_stream().await.unwrap(), "Hello");
+    }
+
+    #[tokio::test]
+    async fn test_collect_stream_error() {
+        let server = MockServer::start();
+
+        server.mock(|when, then| {
+            when.method(GET).path("/stream/1234");
+            then.status(200)
+                .header("Content-Type", "text/event-stream")
+                .body("event: output\nid: 1\ndata: Hello\n\nevent: error\nid: 2\ndata: CUDA out of memory\n\nevent: done\nid: 3\ndata: {}\n\n");
+        });
+
+        let mut prediction = prediction_json(&server.base_url(), "1234", "processing");
+        prediction["urls"]["stream"] = json!(format!("{}/stream/1234", server.base_url()));
+        let mut prediction: Prediction = serde_json::from_value(prediction).unwrap();
+        prediction.config = Some(
+            ReplicateConfig::test(server.base_url())
+                .unwrap()
+                .with_insecure_streams(true),
+        );
+
+        match prediction.collect_stream().await {
+            Err(ReplicateError::Misc(message)) => assert_eq!(message, "CUDA out of memory"),
+            other => panic!("expected Misc, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_collect_stream_with() {
+        let server = MockServer::start();
+
+        server.mock(|when, then| {
+            when.method(GET).path("/stream/1234");
+            then.status(200)
+                .header("Content-Type", "text/event-stream")
+                .body("event: output\nid: 1\ndata: Hello\n\nevent: output\nid: 2\ndata: , world\ndata: \n\nevent: done\nid: 3\ndata: {}\n\n");
+        });
+
+        let mut prediction = prediction_json(&server.base_url(), "1234", "processing");
+        prediction["urls"]["stream"] = json!(format!("{}/stream/1234", server.base_url()));
+        let mut prediction: Prediction = serde_json::from_value(prediction).unwrap();
+        prediction.config = Some(
+            ReplicateConfig::test(server.base_url())
+                .unwrap()
+                .with_insecure_streams(true),
+        );
+
+        let verbatim = prediction.collect_stream().await.unwrap();
+        assert_eq!(verbatim, "Hello, world\n");
+
+        let trimmed = prediction
+            .collect_stream_with(&CollectStreamOptions {
+                trim_trailing_whitespace: true,
+            })
+            .await
+            .unwrap();
+        assert_eq!(trimmed, "Hello, world");
+    }
+
+    #[tokio::test]
+    async fn test_events() {
+        let server = MockServer::start();
+
+        server.mock(|when, then| {
+            when.method(GET).path("/stream/1234");
+            then.status(200)
+                .header("Content-Type", "text/event-stream")
+                .body(indoc! {"
+                    event: output
+                    id: 1
+                    data: Hello
+
+                    event: logs
+                    id: 2
+                    data: generated 1 token
+
+                    event: heartbeat
+                    id: 3
+                    data:
+
+                    event: error
+                    id: 4
+                    data: CUDA out of memory
+
+                    event: done
+                    id: 5
+                    data: {}
+
+                "});
+        });
+
+        let mut prediction = prediction_json(&server.base_url(), "1234", "processing");
+        prediction["urls"]["stream"] = json!(format!("{}/stream/1234", server.base_url()));
+        let mut prediction: Prediction = serde_json::from_value(prediction).unwrap();
+        prediction.config = Some(
+            ReplicateConfig::test(server.base_url())
+                .unwrap()
+                .with_insecure_streams(true),
+        );
+
+        let mut events = Box::pin(prediction.events().await.unwrap());
+        let mut received = Vec::new();
+        while let Some(event) = events.next().await {
+            received.push(event.unwrap());
+        }
+
+        assert_eq!(
+            received,
+            vec![
+                PredictionEvent::Output("Hello".to_string()),
+                PredictionEvent::Logs("generated 1 token".to_string()),
+                PredictionEvent::Error("CUDA out of memory".to_string()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stream_tee() {
+        let server = MockServer::start();
+
+        server.mock(|when, then| {
+            when.method(GET).path("/stream/1234");
+            then.status(200)
+                .header("Content-Type", "text/event-stream")
+                .body(indoc! {"
+                    event: output
+                    id: 1
+                    data: Hello
+
+                    event: output
+                    id: 2
+                    data: , world
+
+                    event: done
+                    id: 3
+                    data: {}
+
+                "});
+        });
+
+        let mut prediction = prediction_json(&server.base_url(), "1234", "processing");
+        prediction["urls"]["stream"] = json!(format!("{}/stream/1234", server.base_url()));
+        let mut prediction: Prediction = serde_json::from_value(prediction).unwrap();
+        prediction.config = Some(
+            ReplicateConfig::test(server.base_url())
+                .unwrap()
+                .with_insecure_streams(true),
+        );
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        let output = prediction.stream_tee(tx).await.unwrap();
+
+        assert_eq!(output, "Hello, world");
+        assert_eq!(rx.iter().collect::<Vec<_>>(), vec!["Hello", ", world"]);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_stream_buffer() {
+        let server = MockServer::start();
+        std::env::set_var("REPLICATE_API_KEY", "test-api-key");
+
+        server.mock(|when, then| {
+            when.method(GET).path("/stream/1234");
+            then.status(200)
+                .header("Content-Type", "text/event-stream")
+                .body(indoc! {"
+                    event: output
+                    id: 1
+                    data: Hello
+
+                    event: output
+                    id: 2
+                    data: Alice
+
+                    event: output
+                    id: 3
+                    data: !
+
+                    event: done
+                    id: 4
+                    data: {}
+
+                "});
+        });
+
+        let mut prediction = prediction_json(&server.base_url(), "1234", "processing");
+        prediction["urls"]["stream"] = json!(format!("{}/stream/1234", server.base_url()));
+        let mut prediction: Prediction = serde_json::from_value(prediction).unwrap();
+        prediction.config = Some(
+            ReplicateConfig::test(server.base_url())
+                .unwrap()
+                .with_insecure_streams(true)
+                .with_stream_buffer(2),
+        );
+
+        // Only two events fit in the buffer, so the reader waits for the consumer
+        let mut stream = prediction.get_stream().await.unwrap();
+        Delay::new(Duration::from_millis(50)).await;
+        assert!(!stream.reader.as_ref().unwrap().0.is_finished());
+        assert_eq!(stream.next().await.unwrap().unwrap().data, "Hello");
+
+        let rest = stream
+            .map(|event| event.unwrap().data)
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(rest, vec!["Alice", "!", "{}"]);
+
+        // With room for every event, the whole stream is read before the consumer polls it
+        prediction.config = Some(
+            ReplicateConfig::test(server.base_url())
+                .unwrap()
+                .with_insecure_streams(true)
+                .with_stream_buffer(8),
+        );
+        let stream = prediction.get_stream().await.unwrap();
+        let reader = &stream.reader.as_ref().unwrap().0;
+        for _ in 0..100 {
+            if reader.is_finished() {
+                break;
+            }
+            Delay::new(Duration::from_millis(10)).await;
+        }
+        assert!(reader.is_finished());
+        assert_eq!(stream.count().await, 4);
+    }
 }
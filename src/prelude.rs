@@ -0,0 +1,11 @@
+//! Commonly used types, re-exported for convenience.
+//!
+//! ```rust
+//! use replicate_rs::prelude::*;
+//! ```
+pub use crate::client::{Replicate, ReplicateClient};
+pub use crate::config::ReplicateConfig;
+pub use crate::errors::{ReplicateError, ReplicateResult};
+pub use crate::models::{Model, ModelClient};
+pub use crate::predictions::{Prediction, PredictionClient, PredictionStatus};
+pub use crate::trainings::{Training, TrainingClient};
@@ -0,0 +1,527 @@
+//! Utilities for working with a model version's OpenAPI schema.
+//!
+use crate::errors::{ReplicateError, ReplicateResult};
+use serde_json::{Map, Value};
+
+/// A single input accepted by a model version
+#[derive(Debug, Clone, PartialEq)]
+pub struct InputProperty {
+    /// Name of the input
+    pub name: String,
+    /// JSON type of the input, if declared
+    pub property_type: Option<String>,
+    /// A brief description of the input
+    pub description: Option<String>,
+    /// The value used if the input is not provided
+    pub default: Option<Value>,
+    /// Whether the input must be provided
+    pub required: bool,
+}
+
+/// Inputs accepted by a model version, parsed from its OpenAPI schema.
+///
+/// Parsing is best-effort: any part of the schema which could not be understood is skipped and
+/// described in `warnings`, rather than failing the whole parse.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct InputSchema {
+    /// All inputs which could be parsed
+    pub properties: Vec<InputProperty>,
+    /// Descriptions of any parts of the schema which were skipped
+    pub warnings: Vec<String>,
+}
+
+impl InputSchema {
+    /// Check `input` against this schema before it is sent to Replicate, returning
+    /// `ReplicateError::ValidationError` listing every problem found.
+    ///
+    /// Required inputs must be present, and inputs with a declared type must match it. Inputs
+    /// which are not in the schema are rejected too, unless parts of the schema were skipped
+    /// while parsing, as they may have been declared there. Field paths match those returned by
+    /// Replicate, such as `input.width`.
+    pub fn validate(&self, input: &Value) -> ReplicateResult<()> {
+        let Some(input) = input.as_object() else {
+            return Err(ReplicateError::ValidationError {
+                detail: "Input validation failed: - input: Invalid type. Expected: object"
+                    .to_string(),
+                invalid_fields: vec!["input".to_string()],
+            });
+        };
+
+        let mut problems = Vec::new();
+        for property in &self.properties {
+            match input.get(&property.name) {
+                None if property.required && property.default.is_none() => {
+                    problems.push((property.name.clone(), "is required".to_string()));
+                }
+                Some(value) if !value.is_null() => {
+                    if let Some(expected) = property.property_type.as_deref() {
+                        if !matches_type(value, expected) {
+                            problems.push((
+                                property.name.clone(),
+                                format!("Invalid type. Expected: {expected}"),
+                            ));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if self.warnings.is_empty() {
+            for name in input.keys() {
+                if !self
+                    .properties
+                    .iter()
+                    .any(|property| &property.name == name)
+                {
+                    problems.push((
+                        name.clone(),
+                        "Additional property is not allowed".to_string(),
+                    ));
+                }
+            }
+        }
+
+        if problems.is_empty() {
+            return Ok(());
+        }
+
+        let detail = problems
+            .iter()
+            .map(|(name, problem)| format!("- input.{name}: {problem}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        Err(ReplicateError::ValidationError {
+            detail: format!("Input validation failed: {detail}"),
+            invalid_fields: problems
+                .into_iter()
+                .map(|(name, _)| format!("input.{name}"))
+                .collect(),
+        })
+    }
+}
+
+/// Whether `value` matches a JSON Schema `type`. Unrecognized types always match, so that
+/// validation never rejects input Replicate might accept.
+fn matches_type(value: &Value, expected: &str) -> bool {
+    match expected {
+        "string" => value.is_string(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "number" => value.is_number(),
+        "boolean" => value.is_boolean(),
+        "array" => value.is_array(),
+        "object" => value.is_object(),
+        _ => true,
+    }
+}
+
+pub(crate) fn parse_input_schema(openapi_schema: &Value) -> InputSchema {
+    let mut schema = InputSchema::default();
+
+    let Some(input) = openapi_schema.pointer("/components/schemas/Input") else {
+        schema
+            .warnings
+            .push("no components.schemas.Input found in openapi schema".to_string());
+        return schema;
+    };
+
+    let required: Vec<&str> = match input.get("required") {
+        Some(Value::Array(required)) => required.iter().filter_map(Value::as_str).collect(),
+        Some(_) => {
+            schema.warnings.push(
+                "Input.required is not an array, treating all inputs as optional".to_string(),
+            );
+            Vec::new()
+        }
+        None => Vec::new(),
+    };
+
+    let Some(properties) = input.get("properties").and_then(Value::as_object) else {
+        schema
+            .warnings
+            .push("Input.properties is missing or not an object".to_string());
+        return schema;
+    };
+
+    for (name, property) in properties {
+        let Some(property) = property.as_object() else {
+            schema
+                .warnings
+                .push(format!("input '{name}' is not an object, skipping"));
+            continue;
+        };
+
+        schema.properties.push(InputProperty {
+            name: name.clone(),
+            property_type: property
+                .get("type")
+                .and_then(Value::as_str)
+                .map(str::to_string),
+            description: property
+                .get("description")
+                .and_then(Value::as_str)
+                .map(str::to_string),
+            default: property.get("default").cloned(),
+            required: required.contains(&name.as_str()),
+        });
+    }
+
+    schema
+}
+
+/// Prefix of references to other component schemas within an OpenAPI document
+const COMPONENT_REF_PREFIX: &str = "#/components/schemas/";
+
+/// Extract `components.schemas.Input` as a standalone JSON Schema document.
+///
+/// References to other component schemas are inlined. References which would recurse forever
+/// are instead rewritten to point at a copy of the definition under `$defs`, and references
+/// which can't be resolved are left untouched.
+pub(crate) fn input_json_schema(openapi_schema: &Value) -> ReplicateResult<Value> {
+    let schemas = openapi_schema
+        .pointer("/components/schemas")
+        .and_then(Value::as_object)
+        .ok_or(ReplicateError::Misc(
+            "no components.schemas found in openapi schema".to_string(),
+        ))?;
+    let input = schemas.get("Input").ok_or(ReplicateError::Misc(
+        "no components.schemas.Input found in openapi schema".to_string(),
+    ))?;
+
+    let mut recursive = Vec::new();
+    let resolved = inline_refs(
+        input,
+        schemas,
+        &mut vec!["Input".to_string()],
+        &mut recursive,
+    );
+
+    let mut defs = Map::new();
+    while let Some(name) = recursive.pop() {
+        if defs.contains_key(&name) {
+            continue;
+        }
+        if let Some(definition) = schemas.get(&name) {
+            defs.insert(name, rewrite_refs(definition, &mut recursive));
+        }
+    }
+
+    let mut document = Map::new();
+    document.insert(
+        "$schema".to_string(),
+        Value::String("https://json-schema.org/draft/2020-12/schema".to_string()),
+    );
+    if let Value::Object(resolved) = resolved {
+        document.extend(resolved);
+    }
+    if !defs.is_empty() {
+        document.insert("$defs".to_string(), Value::Object(defs));
+    }
+
+    Ok(Value::Object(document))
+}
+
+/// Replace component references with the schema they refer to. Names currently being inlined
+/// are tracked in `stack`, and any reference back to one of them is rewritten and recorded in
+/// `recursive` instead.
+fn inline_refs(
+    value: &Value,
+    schemas: &Map<String, Value>,
+    stack: &mut Vec<String>,
+    recursive: &mut Vec<String>,
+) -> Value {
+    match value {
+        Value::Object(object) => {
+            let reference = object
+                .get("$ref")
+                .and_then(Value::as_str)
+                .and_then(|reference| reference.strip_prefix(COMPONENT_REF_PREFIX));
+
+            if let Some(name) = reference {
+                if stack.iter().any(|seen| seen == name) {
+                    return rewrite_refs(value, recursive);
+                }
+                if let Some(definition) = schemas.get(name) {
+                    stack.push(name.to_string());
+                    let inlined = inline_refs(definition, schemas, stack, recursive);
+                    stack.pop();
+
+                    // Keywords alongside the reference, such as a description, take precedence
+                    if let Value::Object(mut inlined) = inlined {
+                        for (key, sibling) in object.iter().filter(|(key, _)| *key != "$ref") {
+                            inlined.insert(
+                                key.clone(),
+                                inline_refs(sibling, schemas, stack, recursive),
+                            );
+                        }
+                        return Value::Object(inlined);
+                    }
+                    return inlined;
+                }
+            }
+
+            Value::Object(
+                object
+                    .iter()
+                    .map(|(key, value)| {
+                        (key.clone(), inline_refs(value, schemas, stack, recursive))
+                    })
+                    .collect(),
+            )
+        }
+        Value::Array(items) => Value::Array(
+            items
+                .iter()
+                .map(|item| inline_refs(item, schemas, stack, recursive))
+                .collect(),
+        ),
+        _ => value.clone(),
+    }
+}
+
+/// Point component references at `$defs`, recording the name of each definition referenced
+fn rewrite_refs(value: &Value, referenced: &mut Vec<String>) -> Value {
+    match value {
+        Value::Object(object) => Value::Object(
+            object
+                .iter()
+                .map(|(key, value)| {
+                    let name = value
+                        .as_str()
+                        .and_then(|reference| reference.strip_prefix(COMPONENT_REF_PREFIX))
+                        .filter(|_| key == "$ref");
+                    match name {
+                        Some(name) => {
+                            referenced.push(name.to_string());
+                            (key.clone(), Value::String(format!("#/$defs/{name}")))
+                        }
+                        None => (key.clone(), rewrite_refs(value, referenced)),
+                    }
+                })
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(
+            items
+                .iter()
+                .map(|item| rewrite_refs(item, referenced))
+                .collect(),
+        ),
+        _ => value.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_input_schema() {
+        let schema = parse_input_schema(&json!({
+            "components": {
+                "schemas": {
+                    "Input": {
+                        "type": "object",
+                        "required": ["prompt"],
+                        "properties": {
+                            "prompt": {
+                                "type": "string",
+                                "description": "Input prompt"
+                            },
+                            "max_tokens": {
+                                "type": "integer",
+                                "default": 128
+                            }
+                        }
+                    }
+                }
+            }
+        }));
+
+        assert!(schema.warnings.is_empty());
+        assert_eq!(
+            schema.properties,
+            vec![
+                InputProperty {
+                    name: "max_tokens".to_string(),
+                    property_type: Some("integer".to_string()),
+                    description: None,
+                    default: Some(json!(128)),
+                    required: false,
+                },
+                InputProperty {
+                    name: "prompt".to_string(),
+                    property_type: Some("string".to_string()),
+                    description: Some("Input prompt".to_string()),
+                    default: None,
+                    required: true,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_malformed_input_schema() {
+        let schema = parse_input_schema(&json!({
+            "components": {
+                "schemas": {
+                    "Input": {
+                        "required": "prompt",
+                        "properties": {
+                            "prompt": {
+                                "type": "string"
+                            },
+                            "broken": "not a property"
+                        }
+                    }
+                }
+            }
+        }));
+
+        assert_eq!(schema.properties.len(), 1);
+        assert_eq!(schema.properties[0].name, "prompt");
+        assert_eq!(schema.warnings.len(), 2);
+
+        let schema = parse_input_schema(&json!({}));
+        assert!(schema.properties.is_empty());
+        assert_eq!(schema.warnings.len(), 1);
+    }
+
+    #[test]
+    fn test_validate() {
+        let schema = parse_input_schema(&json!({
+            "components": {
+                "schemas": {
+                    "Input": {
+                        "type": "object",
+                        "required": ["prompt", "seed"],
+                        "properties": {
+                            "prompt": {"type": "string"},
+                            "seed": {"type": "integer", "default": 42},
+                            "width": {"type": "integer"},
+                            "scheduler": {"allOf": [{"$ref": "#/components/schemas/scheduler"}]}
+                        }
+                    }
+                }
+            }
+        }));
+
+        schema
+            .validate(&json!({"prompt": "a cat", "width": 512, "scheduler": "DDIM"}))
+            .unwrap();
+
+        let err = schema
+            .validate(&json!({"width": 51.2, "height": 512}))
+            .unwrap_err();
+        match &err {
+            ReplicateError::ValidationError { invalid_fields, .. } => {
+                assert_eq!(
+                    invalid_fields,
+                    &vec!["input.prompt", "input.width", "input.height"]
+                );
+            }
+            _ => panic!("expected a validation error, got {err:?}"),
+        }
+        assert_eq!(
+            err.detail(),
+            "Input validation failed: - input.prompt: is required\n- input.width: Invalid type. Expected: integer\n- input.height: Additional property is not allowed"
+        );
+
+        let err = schema.validate(&json!("a cat")).unwrap_err();
+        assert!(matches!(err, ReplicateError::ValidationError { .. }));
+    }
+
+    #[test]
+    fn test_validate_partial_schema() {
+        let schema = parse_input_schema(&json!({
+            "components": {
+                "schemas": {
+                    "Input": {
+                        "properties": {
+                            "prompt": {"type": "string"},
+                            "broken": "not a property"
+                        }
+                    }
+                }
+            }
+        }));
+
+        schema
+            .validate(&json!({"prompt": "a cat", "broken": 1}))
+            .unwrap();
+    }
+
+    #[test]
+    fn test_input_json_schema() {
+        let schema = input_json_schema(&json!({
+            "components": {
+                "schemas": {
+                    "Input": {
+                        "type": "object",
+                        "title": "Input",
+                        "properties": {
+                            "scheduler": {
+                                "allOf": [{"$ref": "#/components/schemas/scheduler"}],
+                                "default": "DDIM"
+                            },
+                            "mask": {
+                                "$ref": "#/components/schemas/Mask"
+                            }
+                        }
+                    },
+                    "scheduler": {
+                        "type": "string",
+                        "enum": ["DDIM", "K_EULER"]
+                    },
+                    "Mask": {
+                        "type": "object",
+                        "properties": {
+                            "children": {
+                                "type": "array",
+                                "items": {"$ref": "#/components/schemas/Mask"}
+                            }
+                        }
+                    }
+                }
+            }
+        }))
+        .unwrap();
+
+        assert_eq!(
+            schema,
+            json!({
+                "$schema": "https://json-schema.org/draft/2020-12/schema",
+                "type": "object",
+                "title": "Input",
+                "properties": {
+                    "scheduler": {
+                        "allOf": [{"type": "string", "enum": ["DDIM", "K_EULER"]}],
+                        "default": "DDIM"
+                    },
+                    "mask": {
+                        "type": "object",
+                        "properties": {
+                            "children": {
+                                "type": "array",
+                                "items": {"$ref": "#/$defs/Mask"}
+                            }
+                        }
+                    }
+                },
+                "$defs": {
+                    "Mask": {
+                        "type": "object",
+                        "properties": {
+                            "children": {
+                                "type": "array",
+                                "items": {"$ref": "#/$defs/Mask"}
+                            }
+                        }
+                    }
+                }
+            })
+        );
+
+        assert!(input_json_schema(&json!({})).is_err());
+    }
+}
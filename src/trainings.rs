@@ -0,0 +1,500 @@
+//! Utilities for interacting with all training endpoints.
+//!
+//! This includes the following:
+//! - [Create Training](https://replicate.com/docs/reference/http#trainings.create)
+//! - [Get Training](https://replicate.com/docs/reference/http#trainings.get)
+//! - [List Trainings](https://replicate.com/docs/reference/http#trainings.list)
+//! - [Cancel Training](https://replicate.com/docs/reference/http#trainings.cancel)
+//!
+use crate::config::ReplicateConfig;
+use crate::errors::{ReplicateError, ReplicateResult};
+use crate::predictions::{
+    open_stream, parse_events, PollBackoff, PredictionEvent, PredictionPollConfig, PredictionStatus,
+};
+
+use futures::future::{self, Either};
+use futures::stream::{self, Stream, StreamExt};
+use futures_timer::Delay;
+use reqwest::Method;
+use serde_json::Value;
+use std::collections::VecDeque;
+
+/// Provided urls to either cancel or retrieve updated details for the specific training.
+#[derive(serde::Deserialize, Debug)]
+pub struct TrainingUrls {
+    /// Url endpoint to cancel the specific training
+    pub cancel: String,
+    /// Url endpoint to retrieve the specific training
+    pub get: String,
+    /// Url endpoint to stream the training's events, if it was created with streaming enabled
+    #[serde(default)]
+    pub stream: Option<String>,
+}
+
+/// Details for a specific training
+#[derive(serde::Deserialize, Debug)]
+pub struct Training {
+    /// Id of the training
+    pub id: String,
+    /// Model being trained
+    pub model: String,
+    /// Specific version being trained
+    pub version: String,
+    /// The inputs provided for the specific training
+    pub input: Value,
+    /// The current status of the training, which moves through the same statuses as a
+    /// prediction
+    pub status: PredictionStatus,
+    /// The created time for the training
+    pub created_at: String,
+    /// The time the training started running
+    #[serde(default)]
+    pub started_at: Option<String>,
+    /// The time the training finished running
+    #[serde(default)]
+    pub completed_at: Option<String>,
+    /// Urls to either retrieve or cancel details for this training
+    pub urls: TrainingUrls,
+    /// The output of the training if completed, such as the version which was created
+    #[serde(default)]
+    pub output: Option<Value>,
+    /// Logs written by the trainer so far
+    #[serde(default)]
+    pub logs: Option<String>,
+    /// Why the training failed, if it did
+    #[serde(default)]
+    pub error: Option<Value>,
+}
+
+/// Paginated list of available trainings
+#[derive(serde::Deserialize, Debug)]
+pub struct Trainings {
+    /// Identify for status in pagination
+    pub next: Option<String>,
+    /// Identify for status of pagination
+    pub previous: Option<String>,
+    /// List of trainings, which is empty if none have been created
+    #[serde(default)]
+    pub results: Vec<Training>,
+}
+
+/// Polling state for `TrainingClient::stream_logs`, when the training has no stream url
+struct LogPoll {
+    config: ReplicateConfig,
+    poll_config: PredictionPollConfig,
+    training: Training,
+    /// The logs which have already been split into lines
+    read: String,
+    /// Lines which have been read but not yet yielded
+    lines: VecDeque<String>,
+    backoff: PollBackoff,
+    finished: bool,
+}
+
+impl LogPoll {
+    /// Queue each complete line added to the training's logs since they were last read, and
+    /// the trailing partial line once the training has finished
+    fn read_lines(&mut self) {
+        let logs = self.training.logs.as_deref().unwrap_or_default();
+        // Logs only grow, so logs which no longer start with those already read have been
+        // shortened or replaced, and are read again from the start
+        if !logs.starts_with(self.read.as_str()) {
+            self.read.clear();
+        }
+
+        let new = &logs[self.read.len()..];
+        let complete = if self.finished {
+            new.len()
+        } else {
+            new.rfind('\n').map_or(0, |end| end + 1)
+        };
+        self.lines
+            .extend(new[..complete].lines().map(str::to_string));
+        self.read.push_str(&new[..complete]);
+    }
+}
+
+#[derive(serde::Serialize)]
+struct TrainingInput<'a> {
+    destination: &'a str,
+    input: Value,
+}
+
+/// A client for interacting with 'trainings' endpoint
+#[derive(Debug)]
+pub struct TrainingClient {
+    config: ReplicateConfig,
+}
+
+impl TrainingClient {
+    /// Create a new `TrainingClient` based upon a `ReplicateConfig` object
+    pub fn from(config: ReplicateConfig) -> Self {
+        TrainingClient { config }
+    }
+
+    /// Create a new training of a specific model version. The trained version is pushed to
+    /// `destination`, an existing model given as `owner/name`.
+    pub async fn create(
+        &self,
+        owner: &str,
+        name: &str,
+        version_id: &str,
+        destination: &str,
+        input: Value,
+    ) -> ReplicateResult<Training> {
+        let endpoint = self.config.endpoint(&format!(
+            "models/{owner}/{name}/versions/{version_id}/trainings"
+        ));
+        let body = serde_json::to_string(&TrainingInput { destination, input })
+            .map_err(ReplicateError::from)?;
+        let data = self
+            .config
+            .send(self.config.request(Method::POST, endpoint)?.body(body))
+            .await?;
+
+        serde_json::from_str(&data).map_err(ReplicateError::from)
+    }
+
+    /// Get details for an existing training
+    pub async fn get(&self, id: &str) -> ReplicateResult<Training> {
+        let endpoint = self.config.endpoint(&format!("trainings/{id}"));
+        let data = self
+            .config
+            .send(self.config.request(Method::GET, endpoint)?)
+            .await?;
+
+        serde_json::from_str(&data).map_err(ReplicateError::from)
+    }
+
+    /// List all existing trainings for the current user
+    pub async fn list(&self) -> ReplicateResult<Trainings> {
+        let endpoint = self.config.endpoint("trainings");
+        let data = self
+            .config
+            .send(self.config.request(Method::GET, endpoint)?)
+            .await?;
+
+        serde_json::from_str(&data).map_err(ReplicateError::from)
+    }
+
+    /// Cancel an existing training
+    pub async fn cancel(&self, id: &str) -> ReplicateResult<Training> {
+        let endpoint = self.config.endpoint(&format!("trainings/{id}/cancel"));
+        let data = self
+            .config
+            .send(self.config.request(Method::POST, endpoint)?)
+            .await?;
+
+        serde_json::from_str(&data).map_err(ReplicateError::from)
+    }
+
+    /// Stream the lines a training logs as they are written, ending once it completes
+    ///
+    /// If the training was created with streaming enabled, the `logs` events of its stream are
+    /// yielded as they arrive. Otherwise the training is polled according to `poll_config`,
+    /// and each line added to its `logs` since the previous poll is yielded. The stream ends
+    /// after the first error.
+    pub async fn stream_logs(
+        &self,
+        training: Training,
+        poll_config: PredictionPollConfig,
+    ) -> ReplicateResult<impl Stream<Item = ReplicateResult<String>>> {
+        if let Some(stream_url) = &training.urls.stream {
+            let events = open_stream(Some(&self.config), stream_url, &training.urls.cancel).await?;
+            let logs = parse_events(events).filter_map(|event| {
+                future::ready(match event {
+                    Ok(PredictionEvent::Logs(line)) => Some(Ok(line)),
+                    Ok(PredictionEvent::Error(data)) => Some(Err(ReplicateError::Misc(data))),
+                    Ok(PredictionEvent::Output(_) | PredictionEvent::Done) => None,
+                    Err(err) => Some(Err(err)),
+                })
+            });
+            return Ok(Either::Left(logs));
+        }
+
+        let mut poll = LogPoll {
+            config: self.config.clone(),
+            poll_config,
+            finished: training.status.is_terminal(),
+            training,
+            read: String::new(),
+            lines: VecDeque::new(),
+            backoff: PollBackoff::default(),
+        };
+        poll.read_lines();
+
+        let logs = stream::unfold(Some(poll), |poll| async move {
+            let mut poll = poll?;
+            loop {
+                if let Some(line) = poll.lines.pop_front() {
+                    return Some((Ok(line), Some(poll)));
+                }
+                if poll.finished {
+                    return None;
+                }
+
+                let interval = poll
+                    .backoff
+                    .next_interval(&poll.poll_config, &poll.training.status);
+                Delay::new(interval).await;
+
+                let client = TrainingClient::from(poll.config.clone());
+                match client.get(&poll.training.id).await {
+                    Ok(training) => {
+                        poll.finished = training.status.is_terminal();
+                        poll.training = training;
+                        poll.read_lines();
+                    }
+                    Err(err) => return Some((Err(err), None)),
+                }
+            }
+        });
+        Ok(Either::Right(logs))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use httpmock::prelude::*;
+    use indoc::indoc;
+    use serde_json::json;
+    use std::time::Duration;
+
+    fn training(status: &str) -> Value {
+        json!({
+            "id": "zz4ibbonubfz7carwiefibzgga",
+            "model": "stability-ai/sdxl",
+            "version": "39ed52f2a78e934b3ba6e2a89f5b1c712de7dfea535525255b1aa35c5565e08b",
+            "input": {
+                "input_images": "https://example.com/images.zip"
+            },
+            "logs": "",
+            "error": null,
+            "status": status,
+            "created_at": "2023-09-08T16:32:56.990893084Z",
+            "urls": {
+                "cancel": "https://api.replicate.com/v1/trainings/zz4ibbonubfz7carwiefibzgga/cancel",
+                "get": "https://api.replicate.com/v1/trainings/zz4ibbonubfz7carwiefibzgga"
+            }
+        })
+    }
+
+    #[tokio::test]
+    async fn test_create() {
+        let server = MockServer::start();
+
+        let training_mock = server.mock(|when, then| {
+            when.method(POST)
+                .path("/models/stability-ai/sdxl/versions/39ed52f2a78e934b3ba6e2a89f5b1c712de7dfea535525255b1aa35c5565e08b/trainings")
+                .json_body(json!({
+                    "destination": "kyle/sdxl-finetune",
+                    "input": {"input_images": "https://example.com/images.zip"}
+                }));
+            then.status(201).json_body_obj(&training("starting"));
+        });
+
+        let config = ReplicateConfig::test(server.base_url()).unwrap();
+        let training_client = TrainingClient::from(config);
+        let training = training_client
+            .create(
+                "stability-ai",
+                "sdxl",
+                "39ed52f2a78e934b3ba6e2a89f5b1c712de7dfea535525255b1aa35c5565e08b",
+                "kyle/sdxl-finetune",
+                json!({"input_images": "https://example.com/images.zip"}),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(training.status, PredictionStatus::Starting);
+        training_mock.assert();
+    }
+
+    #[tokio::test]
+    async fn test_get() {
+        let server = MockServer::start();
+
+        let training_mock = server.mock(|when, then| {
+            when.method(GET)
+                .path("/trainings/zz4ibbonubfz7carwiefibzgga");
+            then.status(200).json_body_obj(&training("processing"));
+        });
+
+        let config = ReplicateConfig::test(server.base_url()).unwrap();
+        let training_client = TrainingClient::from(config);
+        let training = training_client
+            .get("zz4ibbonubfz7carwiefibzgga")
+            .await
+            .unwrap();
+
+        assert_eq!(training.id, "zz4ibbonubfz7carwiefibzgga");
+        assert_eq!(training.status, PredictionStatus::Processing);
+        training_mock.assert();
+    }
+
+    #[tokio::test]
+    async fn test_list() {
+        let server = MockServer::start();
+
+        let training_mock = server.mock(|when, then| {
+            when.method(GET).path("/trainings");
+            then.status(200).json_body_obj(&json!({
+                "next": null,
+                "previous": null,
+                "results": [training("succeeded")]
+            }));
+        });
+
+        let config = ReplicateConfig::test(server.base_url()).unwrap();
+        let training_client = TrainingClient::from(config);
+        let trainings = training_client.list().await.unwrap();
+
+        assert_eq!(trainings.results.len(), 1);
+        training_mock.assert();
+    }
+
+    #[tokio::test]
+    async fn test_cancel() {
+        let server = MockServer::start();
+
+        let training_mock = server.mock(|when, then| {
+            when.method(POST)
+                .path("/trainings/zz4ibbonubfz7carwiefibzgga/cancel");
+            then.status(200).json_body_obj(&training("canceled"));
+        });
+
+        let config = ReplicateConfig::test(server.base_url()).unwrap();
+        let training_client = TrainingClient::from(config);
+        let training = training_client
+            .cancel("zz4ibbonubfz7carwiefibzgga")
+            .await
+            .unwrap();
+
+        assert_eq!(training.status, PredictionStatus::Canceled);
+        training_mock.assert();
+    }
+
+    fn poll_config() -> PredictionPollConfig {
+        PredictionPollConfig {
+            initial_interval: Duration::from_millis(1),
+            max_interval: Duration::from_millis(5),
+            backoff_multiplier: 2.0,
+            processing_interval: Duration::from_millis(1),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stream_logs_polling() {
+        let server = MockServer::start();
+
+        let mut succeeded = training("succeeded");
+        succeeded["logs"] = json!("Loading images\nstep 1/2\nstep 2/2\nDone");
+        let training_mock = server.mock(|when, then| {
+            when.method(GET)
+                .path("/trainings/zz4ibbonubfz7carwiefibzgga");
+            then.status(200).json_body_obj(&succeeded);
+        });
+
+        let mut processing = training("processing");
+        processing["logs"] = json!("Loading images\nstep 1/");
+        let processing: Training = serde_json::from_value(processing).unwrap();
+
+        let config = ReplicateConfig::test(server.base_url()).unwrap();
+        let logs = TrainingClient::from(config)
+            .stream_logs(processing, poll_config())
+            .await
+            .unwrap();
+        let lines: Vec<String> = logs.map(Result::unwrap).collect().await;
+
+        assert_eq!(
+            lines,
+            vec!["Loading images", "step 1/2", "step 2/2", "Done"]
+        );
+        training_mock.assert();
+    }
+
+    #[test]
+    fn test_read_lines_after_logs_replaced() {
+        let mut training: Training = serde_json::from_value(training("processing")).unwrap();
+        training.logs = Some("step 1/3\nstep 2/3\n".to_string());
+        let mut poll = LogPoll {
+            config: ReplicateConfig::test("http://localhost".to_string()).unwrap(),
+            poll_config: poll_config(),
+            training,
+            read: String::new(),
+            lines: VecDeque::new(),
+            backoff: PollBackoff::default(),
+            finished: false,
+        };
+        poll.read_lines();
+        assert_eq!(
+            poll.lines.drain(..).collect::<Vec<_>>(),
+            vec!["step 1/3", "step 2/3"]
+        );
+
+        // Shorter logs are read again from the start
+        poll.training.logs = Some("retry\n".to_string());
+        poll.read_lines();
+        assert_eq!(poll.lines.drain(..).collect::<Vec<_>>(), vec!["retry"]);
+
+        // As are logs of any length which no longer start with those already read
+        poll.training.logs = Some("Retrying\nstep 1/3\n".to_string());
+        poll.read_lines();
+        assert_eq!(
+            poll.lines.drain(..).collect::<Vec<_>>(),
+            vec!["Retrying", "step 1/3"]
+        );
+
+        poll.training.logs = Some("Retrying\nstep 1/3\nstep 2/3\n".to_string());
+        poll.read_lines();
+        assert_eq!(poll.lines.drain(..).collect::<Vec<_>>(), vec!["step 2/3"]);
+    }
+
+    #[tokio::test]
+    async fn test_stream_logs_from_stream() {
+        let server = MockServer::start();
+
+        let stream_mock = server.mock(|when, then| {
+            when.method(GET)
+                .path("/stream/zz4ibbonubfz7carwiefibzgga")
+                .header("Accept", "text/event-stream");
+            then.status(200)
+                .header("Content-Type", "text/event-stream")
+                .body(indoc! {"
+                    event: logs
+                    id: 1
+                    data: Loading images
+
+                    event: output
+                    id: 2
+                    data: {}
+
+                    event: logs
+                    id: 3
+                    data: step 1/2
+
+                    event: done
+                    id: 4
+                    data: {}
+
+                "});
+        });
+
+        let mut streaming = training("processing");
+        streaming["urls"]["stream"] = json!(server.url("/stream/zz4ibbonubfz7carwiefibzgga"));
+        let streaming: Training = serde_json::from_value(streaming).unwrap();
+
+        let config = ReplicateConfig::test(server.base_url())
+            .unwrap()
+            .with_insecure_streams(true);
+        let logs = TrainingClient::from(config)
+            .stream_logs(streaming, poll_config())
+            .await
+            .unwrap();
+        let lines: Vec<String> = logs.map(Result::unwrap).collect().await;
+
+        assert_eq!(lines, vec!["Loading images", "step 1/2"]);
+        stream_mock.assert();
+    }
+}